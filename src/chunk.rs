@@ -5,19 +5,79 @@ use crate::value::{Value, ValueArray};
 #[derive(Debug)]
 pub enum OpCode {
     OP_CONSTANT,
+    OP_NIL,
+    OP_TRUE,
+    OP_FALSE,
+    OP_POP,
+    OP_GET_LOCAL,
+    OP_SET_LOCAL,
+    OP_GET_GLOBAL,
+    OP_DEFINE_GLOBAL,
+    OP_SET_GLOBAL,
+    OP_GET_UPVALUE,
+    OP_SET_UPVALUE,
+    OP_GET_PROPERTY,
+    OP_SET_PROPERTY,
+    OP_GET_SUPER,
+    OP_EQUAL,
+    OP_GREATER,
+    OP_LESS,
     OP_ADD,
     OP_SUBTRACT,
     OP_MULTIPLY,
     OP_DIVIDE,
+    OP_NOT,
     OP_NEGATE,
+    OP_PRINT,
+    OP_JUMP,
+    OP_JUMP_IF_FALSE,
+    OP_LOOP,
+    OP_CALL,
+    OP_INVOKE,
+    OP_SUPER_INVOKE,
+    OP_CLOSURE,
+    OP_CLOSE_UPVALUE,
     OP_RETURN,
+    OP_CLASS,
+    OP_INHERIT,
+    OP_METHOD,
 }
 
 impl TryFrom<u8> for OpCode {
     type Error = ();
     fn try_from(value: u8) -> Result<Self, Self::Error> {
         const OP_RETURN: u8 = OpCode::OP_RETURN as u8;
+        const OP_CLASS: u8 = OpCode::OP_CLASS as u8;
+        const OP_INHERIT: u8 = OpCode::OP_INHERIT as u8;
+        const OP_METHOD: u8 = OpCode::OP_METHOD as u8;
         const OP_CONSTANT: u8 = OpCode::OP_CONSTANT as u8;
+        const OP_NIL: u8 = OpCode::OP_NIL as u8;
+        const OP_TRUE: u8 = OpCode::OP_TRUE as u8;
+        const OP_FALSE: u8 = OpCode::OP_FALSE as u8;
+        const OP_EQUAL: u8 = OpCode::OP_EQUAL as u8;
+        const OP_GREATER: u8 = OpCode::OP_GREATER as u8;
+        const OP_LESS: u8 = OpCode::OP_LESS as u8;
+        const OP_NOT: u8 = OpCode::OP_NOT as u8;
+        const OP_POP: u8 = OpCode::OP_POP as u8;
+        const OP_PRINT: u8 = OpCode::OP_PRINT as u8;
+        const OP_JUMP: u8 = OpCode::OP_JUMP as u8;
+        const OP_JUMP_IF_FALSE: u8 = OpCode::OP_JUMP_IF_FALSE as u8;
+        const OP_LOOP: u8 = OpCode::OP_LOOP as u8;
+        const OP_CALL: u8 = OpCode::OP_CALL as u8;
+        const OP_INVOKE: u8 = OpCode::OP_INVOKE as u8;
+        const OP_SUPER_INVOKE: u8 = OpCode::OP_SUPER_INVOKE as u8;
+        const OP_GET_UPVALUE: u8 = OpCode::OP_GET_UPVALUE as u8;
+        const OP_SET_UPVALUE: u8 = OpCode::OP_SET_UPVALUE as u8;
+        const OP_GET_PROPERTY: u8 = OpCode::OP_GET_PROPERTY as u8;
+        const OP_SET_PROPERTY: u8 = OpCode::OP_SET_PROPERTY as u8;
+        const OP_GET_SUPER: u8 = OpCode::OP_GET_SUPER as u8;
+        const OP_CLOSURE: u8 = OpCode::OP_CLOSURE as u8;
+        const OP_CLOSE_UPVALUE: u8 = OpCode::OP_CLOSE_UPVALUE as u8;
+        const OP_GET_LOCAL: u8 = OpCode::OP_GET_LOCAL as u8;
+        const OP_SET_LOCAL: u8 = OpCode::OP_SET_LOCAL as u8;
+        const OP_GET_GLOBAL: u8 = OpCode::OP_GET_GLOBAL as u8;
+        const OP_DEFINE_GLOBAL: u8 = OpCode::OP_DEFINE_GLOBAL as u8;
+        const OP_SET_GLOBAL: u8 = OpCode::OP_SET_GLOBAL as u8;
         const OP_NEGATE: u8 = OpCode::OP_NEGATE as u8;
         const OP_ADD: u8 = OpCode::OP_ADD as u8;
         const OP_SUBTRACT: u8 = OpCode::OP_SUBTRACT as u8;
@@ -26,7 +86,37 @@ impl TryFrom<u8> for OpCode {
 
         match value {
             OP_RETURN => Ok(OpCode::OP_RETURN),
+            OP_CLASS => Ok(OpCode::OP_CLASS),
+            OP_INHERIT => Ok(OpCode::OP_INHERIT),
+            OP_METHOD => Ok(OpCode::OP_METHOD),
             OP_CONSTANT => Ok(OpCode::OP_CONSTANT),
+            OP_NIL => Ok(OpCode::OP_NIL),
+            OP_TRUE => Ok(OpCode::OP_TRUE),
+            OP_FALSE => Ok(OpCode::OP_FALSE),
+            OP_EQUAL => Ok(OpCode::OP_EQUAL),
+            OP_GREATER => Ok(OpCode::OP_GREATER),
+            OP_LESS => Ok(OpCode::OP_LESS),
+            OP_NOT => Ok(OpCode::OP_NOT),
+            OP_POP => Ok(OpCode::OP_POP),
+            OP_PRINT => Ok(OpCode::OP_PRINT),
+            OP_JUMP => Ok(OpCode::OP_JUMP),
+            OP_JUMP_IF_FALSE => Ok(OpCode::OP_JUMP_IF_FALSE),
+            OP_LOOP => Ok(OpCode::OP_LOOP),
+            OP_CALL => Ok(OpCode::OP_CALL),
+            OP_INVOKE => Ok(OpCode::OP_INVOKE),
+            OP_SUPER_INVOKE => Ok(OpCode::OP_SUPER_INVOKE),
+            OP_GET_UPVALUE => Ok(OpCode::OP_GET_UPVALUE),
+            OP_SET_UPVALUE => Ok(OpCode::OP_SET_UPVALUE),
+            OP_GET_PROPERTY => Ok(OpCode::OP_GET_PROPERTY),
+            OP_SET_PROPERTY => Ok(OpCode::OP_SET_PROPERTY),
+            OP_GET_SUPER => Ok(OpCode::OP_GET_SUPER),
+            OP_CLOSURE => Ok(OpCode::OP_CLOSURE),
+            OP_CLOSE_UPVALUE => Ok(OpCode::OP_CLOSE_UPVALUE),
+            OP_GET_LOCAL => Ok(OpCode::OP_GET_LOCAL),
+            OP_SET_LOCAL => Ok(OpCode::OP_SET_LOCAL),
+            OP_GET_GLOBAL => Ok(OpCode::OP_GET_GLOBAL),
+            OP_DEFINE_GLOBAL => Ok(OpCode::OP_DEFINE_GLOBAL),
+            OP_SET_GLOBAL => Ok(OpCode::OP_SET_GLOBAL),
             OP_NEGATE => Ok(OpCode::OP_NEGATE),
             OP_ADD => Ok(OpCode::OP_ADD),
             OP_SUBTRACT => Ok(OpCode::OP_SUBTRACT),
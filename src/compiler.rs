@@ -1,14 +1,15 @@
-use std::borrow::Borrow;
-
 use crate::{
-    chunk::{self, Chunk, OpCode},
-    debug::disassemble_chunk,
-    scanner::{self, Scanner, Token, TokenType},
+    chunk::{Chunk, OpCode},
+    error::{CompileError, Diagnostic},
+    object::{Gc, Obj, ObjFunction},
+    scanner::{Scanner, Token, TokenType},
     value::Value,
+    vm::VM,
 };
 
 #[derive(Debug, Clone)]
 #[repr(u8)]
+#[allow(non_camel_case_types)]
 enum Precedence {
     PREC_NONE,
     PREC_ASSIGNMENT, // =
@@ -54,68 +55,150 @@ impl From<u8> for Precedence {
     }
 }
 
+type ParseFn =
+    fn(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler, can_assign: bool);
+
 struct ParseRule {
-    prefix: Option<fn(parser: &mut Parser, scanner: &mut Scanner, compiling_chunk: &mut Chunk)>,
-    infix: Option<fn(parser: &mut Parser, scanner: &mut Scanner, compiling_chunk: &mut Chunk)>,
+    prefix: Option<ParseFn>,
+    infix: Option<ParseFn>,
     precedence: Precedence,
 }
 
-const RULE: [(
-    Option<fn(parser: &mut Parser, scanner: &mut Scanner, compiling_chunk: &mut Chunk)>,
-    Option<fn(parser: &mut Parser, scanner: &mut Scanner, compiling_chunk: &mut Chunk)>,
-    Precedence,
-); 40] = [
-    (Some(grouping), None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
+const RULE: [(Option<ParseFn>, Option<ParseFn>, Precedence); 40] = [
+    (Some(grouping), Some(call), Precedence::PREC_CALL),
     (None, None, Precedence::PREC_NONE),
     (None, None, Precedence::PREC_NONE),
     (None, None, Precedence::PREC_NONE),
     (None, None, Precedence::PREC_NONE),
+    (None, Some(dot), Precedence::PREC_CALL),
     (Some(unary), Some(binary), Precedence::PREC_TERM),
     (None, Some(binary), Precedence::PREC_TERM),
     (None, None, Precedence::PREC_NONE),
     (None, Some(binary), Precedence::PREC_FACTOR),
     (None, Some(binary), Precedence::PREC_FACTOR),
+    (Some(unary), None, Precedence::PREC_NONE),
+    (None, Some(binary), Precedence::PREC_EQUALITY),
     (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
+    (None, Some(binary), Precedence::PREC_EQUALITY),
+    (None, Some(binary), Precedence::PREC_COMPARISON),
+    (None, Some(binary), Precedence::PREC_COMPARISON),
+    (None, Some(binary), Precedence::PREC_COMPARISON),
+    (None, Some(binary), Precedence::PREC_COMPARISON),
+    (Some(variable), None, Precedence::PREC_NONE),
+    (Some(string), None, Precedence::PREC_NONE),
     (Some(number), None, Precedence::PREC_NONE),
+    (None, Some(and), Precedence::PREC_AND),
     (None, None, Precedence::PREC_NONE),
     (None, None, Precedence::PREC_NONE),
+    (Some(literal), None, Precedence::PREC_NONE),
     (None, None, Precedence::PREC_NONE),
     (None, None, Precedence::PREC_NONE),
     (None, None, Precedence::PREC_NONE),
+    (Some(literal), None, Precedence::PREC_NONE),
+    (None, Some(or), Precedence::PREC_OR),
     (None, None, Precedence::PREC_NONE),
     (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
-    (None, None, Precedence::PREC_NONE),
+    (Some(super_), None, Precedence::PREC_NONE),
+    (Some(this_), None, Precedence::PREC_NONE),
+    (Some(literal), None, Precedence::PREC_NONE),
     (None, None, Precedence::PREC_NONE),
     (None, None, Precedence::PREC_NONE),
     (None, None, Precedence::PREC_NONE),
     (None, None, Precedence::PREC_NONE),
 ];
 
-#[derive(Debug, Clone)]
-pub struct Parser {
+const UINT8_COUNT: usize = u8::MAX as usize + 1;
+
+struct Local {
+    name: Token,
+    /// Scope depth the local was declared at, or -1 while its initializer is
+    /// still being compiled.
+    depth: i32,
+    is_captured: bool,
+}
+
+/// Where a closure finds a captured variable when it is created: a local
+/// slot of the enclosing function, or one of the enclosing function's own
+/// upvalues.
+struct Upvalue {
+    index: u8,
+    is_local: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(non_camel_case_types)]
+enum FunctionType {
+    TYPE_FUNCTION,
+    TYPE_INITIALIZER,
+    TYPE_METHOD,
+    TYPE_SCRIPT,
+}
+
+pub struct Compiler {
+    enclosing: Option<Box<Compiler>>,
+    function: Gc<ObjFunction>,
+    function_type: FunctionType,
+    locals: Vec<Local>,
+    upvalues: Vec<Upvalue>,
+    scope_depth: i32,
+}
+
+impl Compiler {
+    fn new(function_type: FunctionType, parser: &mut Parser, scanner: &Scanner) -> Self {
+        // The function is allocated first and registered as a root so that a
+        // collection triggered by interning its name cannot free it.
+        let mut function = parser.vm.alloc(ObjFunction::new(None));
+        parser.vm.compiler_roots.push(function);
+        if function_type != FunctionType::TYPE_SCRIPT {
+            let token = parser.previous.as_ref().unwrap();
+            let name = &scanner.source[token.start..token.start + token.length];
+            function.name = Some(parser.vm.copy_string(name));
+        }
+
+        let mut locals = Vec::with_capacity(UINT8_COUNT);
+        // Slot zero holds the function being called, or the receiver for
+        // methods, where it is reachable as `this`.
+        let slot_zero = match function_type {
+            FunctionType::TYPE_FUNCTION | FunctionType::TYPE_SCRIPT => TokenType::TOKEN_IDENTIFIER,
+            FunctionType::TYPE_INITIALIZER | FunctionType::TYPE_METHOD => TokenType::TOKEN_THIS,
+        };
+        locals.push(Local {
+            name: synthetic_token(slot_zero),
+            depth: 0,
+            is_captured: false,
+        });
+
+        Self {
+            enclosing: None,
+            function,
+            function_type,
+            locals,
+            upvalues: Vec::new(),
+            scope_depth: 0,
+        }
+    }
+}
+
+/// The class whose body is being compiled, used to reject `this` and
+/// `super` where there is no receiver or superclass to refer to.
+struct ClassCompiler {
+    enclosing: Option<Box<ClassCompiler>>,
+    has_superclass: bool,
+}
+
+pub struct Parser<'a> {
     previous: Option<Token>,
     current: Option<Token>,
-    had_error: bool,
+    diagnostics: Vec<Diagnostic>,
     panic_mode: bool,
+    class_compiler: Option<Box<ClassCompiler>>,
+    /// Compiling a REPL line: a trailing expression without a `;` is
+    /// printed instead of rejected.
+    repl: bool,
+    vm: &'a mut VM,
 }
 
-impl Parser {
+impl Parser<'_> {
     fn advance(&mut self, scanner: &mut Scanner) {
         self.previous = self.current.clone();
 
@@ -130,27 +213,34 @@ impl Parser {
     }
 }
 
-pub fn compile(source: String, chunk: &mut Chunk) -> bool {
+pub fn compile(source: String, repl: bool, vm: &mut VM) -> Result<Gc<ObjFunction>, CompileError> {
     let mut scanner = Scanner::init_scanner(source);
-    let mut compiling_chunk = chunk;
 
     let mut parser = Parser {
         previous: None,
         current: None,
-        had_error: false,
+        diagnostics: Vec::new(),
         panic_mode: false,
+        class_compiler: None,
+        repl,
+        vm,
     };
+    let compiler = &mut Compiler::new(FunctionType::TYPE_SCRIPT, &mut parser, &scanner);
     parser.advance(&mut scanner);
 
-    expression(&mut parser, &mut scanner, compiling_chunk);
-    consume(
-        TokenType::TOKEN_EOF,
-        "Expected end of expression.",
-        &mut parser,
-        &mut scanner,
-    );
-    end_compiler(&mut parser, compiling_chunk);
-    parser.had_error
+    while !match_token(TokenType::TOKEN_EOF, &mut parser, &mut scanner) {
+        declaration(&mut parser, &mut scanner, compiler);
+    }
+
+    let function = end_compiler(&mut parser, compiler);
+    parser.vm.compiler_roots.clear();
+    if parser.diagnostics.is_empty() {
+        Ok(function)
+    } else {
+        Err(CompileError {
+            diagnostics: parser.diagnostics,
+        })
+    }
 }
 
 fn error_at_current(start: &str, parser: &mut Parser, scanner: &Scanner) {
@@ -167,20 +257,17 @@ fn error_at(token: &Token, message: &str, scanner: &Scanner, parser: &mut Parser
 
     parser.panic_mode = true;
 
-    eprint!("[line {}] Error", token.line);
-    if token.typo == TokenType::TOKEN_EOF {
-        eprint!(" at end");
-    } else if token.typo == TokenType::TOKEN_ERROR {
-    } else {
-        eprint!(
-            " at {}",
-            &scanner.source[token.start..token.start + token.length]
-        )
-    }
-
-    eprintln!("{}", message);
-    parser.had_error = true;
-    //parser.hadError = true;
+    let lexeme = match token.typo {
+        TokenType::TOKEN_EOF | TokenType::TOKEN_ERROR => None,
+        _ => Some(scanner.source[token.start..token.start + token.length].to_string()),
+    };
+    parser.diagnostics.push(Diagnostic {
+        line: token.line,
+        column: token.column,
+        span: token.start..token.start + token.length,
+        message: message.to_string(),
+        lexeme,
+    });
 }
 
 fn consume(typo: TokenType, message: &str, parser: &mut Parser, scanner: &mut Scanner) {
@@ -191,53 +278,953 @@ fn consume(typo: TokenType, message: &str, parser: &mut Parser, scanner: &mut Sc
     error_at_current(message, parser, scanner);
 }
 
-fn emit_byte(byte: u8, parser: &Parser, compiling_chunk: &mut Chunk) {
-    current_chunk(compiling_chunk).write_chunk(byte, parser.previous.as_ref().unwrap().line);
+fn check(typo: TokenType, parser: &Parser) -> bool {
+    parser.current.as_ref().unwrap().typo == typo
+}
+
+fn match_token(typo: TokenType, parser: &mut Parser, scanner: &mut Scanner) -> bool {
+    if !check(typo, parser) {
+        return false;
+    }
+    parser.advance(scanner);
+    true
+}
+
+fn emit_byte(byte: u8, parser: &Parser, compiler: &mut Compiler) {
+    current_chunk(compiler).write_chunk(byte, parser.previous.as_ref().unwrap().line);
+}
+
+fn current_chunk(compiler: &mut Compiler) -> &mut Chunk {
+    &mut compiler.function.chunk
+}
+
+/// Finishes the innermost function, restores its enclosing compiler (if
+/// any) as the current one and returns the compiled function.
+fn end_compiler(parser: &mut Parser, compiler: &mut Compiler) -> Gc<ObjFunction> {
+    emit_return(parser, compiler);
+    let function = compiler.function;
+    parser.vm.compiler_roots.pop();
+
+    if let Some(enclosing) = compiler.enclosing.take() {
+        *compiler = *enclosing;
+    }
+    function
+}
+
+fn emit_return(parser: &mut Parser, compiler: &mut Compiler) {
+    if compiler.function_type == FunctionType::TYPE_INITIALIZER {
+        emit_bytes(OpCode::OP_GET_LOCAL as u8, 0, parser, compiler);
+    } else {
+        emit_byte(OpCode::OP_NIL as u8, parser, compiler);
+    }
+    emit_byte(OpCode::OP_RETURN as u8, parser, compiler);
+}
+
+/// Emits `instruction` with a placeholder 16-bit operand and returns the
+/// offset of that operand so `patch_jump` can fill it in later.
+fn emit_jump(instruction: OpCode, parser: &Parser, compiler: &mut Compiler) -> usize {
+    emit_byte(instruction as u8, parser, compiler);
+    emit_byte(0xff, parser, compiler);
+    emit_byte(0xff, parser, compiler);
+    current_chunk(compiler).code.len() - 2
+}
+
+fn patch_jump(offset: usize, parser: &mut Parser, scanner: &Scanner, compiler: &mut Compiler) {
+    // -2 to adjust for the bytecode for the jump offset itself.
+    let jump = current_chunk(compiler).code.len() - offset - 2;
+
+    if jump > u16::MAX as usize {
+        error(
+            &parser.previous.clone().unwrap(),
+            "Too much code to jump over.",
+            scanner,
+            parser,
+        );
+    }
+
+    let chunk = current_chunk(compiler);
+    chunk.code[offset] = ((jump >> 8) & 0xff) as u8;
+    chunk.code[offset + 1] = (jump & 0xff) as u8;
 }
 
-fn current_chunk(compiling_chunk: &mut Chunk) -> &mut Chunk {
-    compiling_chunk
+fn emit_loop(loop_start: usize, parser: &mut Parser, scanner: &Scanner, compiler: &mut Compiler) {
+    emit_byte(OpCode::OP_LOOP as u8, parser, compiler);
+
+    let offset = current_chunk(compiler).code.len() - loop_start + 2;
+    if offset > u16::MAX as usize {
+        error(
+            &parser.previous.clone().unwrap(),
+            "Loop body too large.",
+            scanner,
+            parser,
+        );
+    }
+
+    emit_byte(((offset >> 8) & 0xff) as u8, parser, compiler);
+    emit_byte((offset & 0xff) as u8, parser, compiler);
 }
 
-fn end_compiler(parser: &mut Parser, compiling_chunk: &mut Chunk) {
-    emit_return(parser, compiling_chunk);
+fn emit_bytes(byte_1: u8, byte_2: u8, parser: &Parser, compiler: &mut Compiler) {
+    emit_byte(byte_1, parser, compiler);
+    emit_byte(byte_2, parser, compiler);
+}
 
-    #[cfg(feature = "debug")]
+fn expression(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    parse_precedence(Precedence::PREC_ASSIGNMENT, parser, scanner, compiler);
+}
+
+fn declaration(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    if match_token(TokenType::TOKEN_CLASS, parser, scanner) {
+        class_declaration(parser, scanner, compiler);
+    } else if match_token(TokenType::TOKEN_FUN, parser, scanner) {
+        fun_declaration(parser, scanner, compiler);
+    } else if match_token(TokenType::TOKEN_VAR, parser, scanner) {
+        var_declaration(parser, scanner, compiler);
+    } else {
+        statement(parser, scanner, compiler);
+    }
+
+    if parser.panic_mode {
+        synchronize(parser, scanner);
+    }
+}
+
+fn class_declaration(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    consume(
+        TokenType::TOKEN_IDENTIFIER,
+        "Expect class name.",
+        parser,
+        scanner,
+    );
+    let name = parser.previous.clone().unwrap();
+    let name_constant = identifier_constant(&name, parser, scanner, compiler);
+    declare_variable(parser, scanner, compiler);
+
+    emit_bytes(OpCode::OP_CLASS as u8, name_constant, parser, compiler);
+    define_variable(name_constant, parser, compiler);
+
+    let enclosing = parser.class_compiler.take();
+    parser.class_compiler = Some(Box::new(ClassCompiler {
+        enclosing,
+        has_superclass: false,
+    }));
+
+    if match_token(TokenType::TOKEN_LESS, parser, scanner) {
+        consume(
+            TokenType::TOKEN_IDENTIFIER,
+            "Expect superclass name.",
+            parser,
+            scanner,
+        );
+        variable(parser, scanner, compiler, false);
+
+        if identifiers_equal(&name, parser.previous.as_ref().unwrap(), scanner) {
+            error(
+                &parser.previous.clone().unwrap(),
+                "A class can't inherit from itself.",
+                scanner,
+                parser,
+            );
+        }
+
+        // The superclass lives in a local named `super` scoped to the class
+        // body, so methods capture it like any other variable.
+        begin_scope(compiler);
+        add_local(
+            synthetic_token(TokenType::TOKEN_SUPER),
+            parser,
+            scanner,
+            compiler,
+        );
+        define_variable(0, parser, compiler);
+
+        named_variable(&name, parser, scanner, compiler, false);
+        emit_byte(OpCode::OP_INHERIT as u8, parser, compiler);
+        parser.class_compiler.as_mut().unwrap().has_superclass = true;
+    }
+
+    // Keep the class on the stack while its methods are attached to it.
+    named_variable(&name, parser, scanner, compiler, false);
+    consume(
+        TokenType::TOKEN_LEFT_BRACE,
+        "Expect '{' before class body.",
+        parser,
+        scanner,
+    );
+    while !check(TokenType::TOKEN_RIGHT_BRACE, parser) && !check(TokenType::TOKEN_EOF, parser) {
+        method(parser, scanner, compiler);
+    }
+    consume(
+        TokenType::TOKEN_RIGHT_BRACE,
+        "Expect '}' after class body.",
+        parser,
+        scanner,
+    );
+    emit_byte(OpCode::OP_POP as u8, parser, compiler);
+
+    let class_compiler = parser.class_compiler.take().unwrap();
+    if class_compiler.has_superclass {
+        end_scope(parser, compiler);
+    }
+    parser.class_compiler = class_compiler.enclosing;
+}
+
+fn method(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    consume(
+        TokenType::TOKEN_IDENTIFIER,
+        "Expect method name.",
+        parser,
+        scanner,
+    );
+    let name = parser.previous.clone().unwrap();
+    let constant = identifier_constant(&name, parser, scanner, compiler);
+
+    let function_type = if &scanner.source[name.start..name.start + name.length] == "init" {
+        FunctionType::TYPE_INITIALIZER
+    } else {
+        FunctionType::TYPE_METHOD
+    };
+    function(function_type, parser, scanner, compiler);
+    emit_bytes(OpCode::OP_METHOD as u8, constant, parser, compiler);
+}
+
+fn fun_declaration(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    let global = parse_variable("Expect function name.", parser, scanner, compiler);
+    mark_initialized(compiler);
+    function(FunctionType::TYPE_FUNCTION, parser, scanner, compiler);
+    define_variable(global, parser, compiler);
+}
+
+/// Compiles a function's parameters and body with a fresh compiler and
+/// leaves the resulting function object on the stack.
+fn function(
+    function_type: FunctionType,
+    parser: &mut Parser,
+    scanner: &mut Scanner,
+    compiler: &mut Compiler,
+) {
+    let enclosing = std::mem::replace(compiler, Compiler::new(function_type, parser, scanner));
+    compiler.enclosing = Some(Box::new(enclosing));
+    begin_scope(compiler);
+
+    consume(
+        TokenType::TOKEN_LEFT_PAREN,
+        "Expect '(' after function name.",
+        parser,
+        scanner,
+    );
+    if !check(TokenType::TOKEN_RIGHT_PAREN, parser) {
+        loop {
+            compiler.function.arity += 1;
+            if compiler.function.arity > 255 {
+                error_at_current("Can't have more than 255 parameters.", parser, scanner);
+            }
+            let constant = parse_variable("Expect parameter name.", parser, scanner, compiler);
+            define_variable(constant, parser, compiler);
+
+            if !match_token(TokenType::TOKEN_COMMA, parser, scanner) {
+                break;
+            }
+        }
+    }
+    consume(
+        TokenType::TOKEN_RIGHT_PAREN,
+        "Expect ')' after parameters.",
+        parser,
+        scanner,
+    );
+    consume(
+        TokenType::TOKEN_LEFT_BRACE,
+        "Expect '{' before function body.",
+        parser,
+        scanner,
+    );
+    block(parser, scanner, compiler);
+
+    let upvalues = std::mem::take(&mut compiler.upvalues);
+    let function = end_compiler(parser, compiler);
+    let constant = make_constant(
+        Value::Obj(Obj::Function(function)),
+        compiler,
+        parser,
+        scanner,
+    );
+    emit_bytes(OpCode::OP_CLOSURE as u8, constant, parser, compiler);
+
+    for upvalue in upvalues {
+        emit_byte(upvalue.is_local as u8, parser, compiler);
+        emit_byte(upvalue.index, parser, compiler);
+    }
+}
+
+fn var_declaration(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    let global = parse_variable("Expect variable name.", parser, scanner, compiler);
+
+    if match_token(TokenType::TOKEN_EQUAL, parser, scanner) {
+        expression(parser, scanner, compiler);
+    } else {
+        emit_byte(OpCode::OP_NIL as u8, parser, compiler);
+    }
+    consume(
+        TokenType::TOKEN_SEMICOLON,
+        "Expect ';' after variable declaration.",
+        parser,
+        scanner,
+    );
+
+    define_variable(global, parser, compiler);
+}
+
+fn parse_variable(
+    error_message: &str,
+    parser: &mut Parser,
+    scanner: &mut Scanner,
+    compiler: &mut Compiler,
+) -> u8 {
+    consume(TokenType::TOKEN_IDENTIFIER, error_message, parser, scanner);
+
+    declare_variable(parser, scanner, compiler);
+    if compiler.scope_depth > 0 {
+        return 0;
+    }
+
+    let name = parser.previous.clone().unwrap();
+    identifier_constant(&name, parser, scanner, compiler)
+}
+
+fn identifier_constant(
+    name: &Token,
+    parser: &mut Parser,
+    scanner: &Scanner,
+    compiler: &mut Compiler,
+) -> u8 {
+    let string = parser
+        .vm
+        .copy_string(&scanner.source[name.start..name.start + name.length]);
+    make_constant(Value::Obj(Obj::String(string)), compiler, parser, scanner)
+}
+
+/// A token for a name the compiler declares itself rather than reading from
+/// the source, such as the receiver slot of a method.
+fn synthetic_token(typo: TokenType) -> Token {
+    Token {
+        typo,
+        start: 0,
+        length: 0,
+        line: 0,
+        column: 0,
+        error: None,
+    }
+}
+
+fn identifiers_equal(a: &Token, b: &Token, scanner: &Scanner) -> bool {
+    // Keyword names like `this` have no lexeme to compare, only a kind.
+    if a.typo != TokenType::TOKEN_IDENTIFIER || b.typo != TokenType::TOKEN_IDENTIFIER {
+        return a.typo == b.typo;
+    }
+    a.length == b.length
+        && scanner.source[a.start..a.start + a.length]
+            == scanner.source[b.start..b.start + b.length]
+}
+
+fn add_local(name: Token, parser: &mut Parser, scanner: &Scanner, compiler: &mut Compiler) {
+    if compiler.locals.len() == UINT8_COUNT {
+        error(
+            &parser.previous.clone().unwrap(),
+            "Too many local variables in function.",
+            scanner,
+            parser,
+        );
+        return;
+    }
+
+    compiler.locals.push(Local {
+        name,
+        depth: -1,
+        is_captured: false,
+    });
+}
+
+fn declare_variable(parser: &mut Parser, scanner: &Scanner, compiler: &mut Compiler) {
+    if compiler.scope_depth == 0 {
+        return;
+    }
+
+    let name = parser.previous.clone().unwrap();
+    for local in compiler.locals.iter().rev() {
+        if local.depth != -1 && local.depth < compiler.scope_depth {
+            break;
+        }
+
+        if identifiers_equal(&name, &local.name, scanner) {
+            error(
+                &name,
+                "Already a variable with this name in this scope.",
+                scanner,
+                parser,
+            );
+            break;
+        }
+    }
+
+    add_local(name, parser, scanner, compiler);
+}
+
+fn resolve_local(
+    name: &Token,
+    parser: &mut Parser,
+    scanner: &Scanner,
+    compiler: &mut Compiler,
+) -> Option<u8> {
+    let slot = compiler
+        .locals
+        .iter()
+        .rposition(|local| identifiers_equal(name, &local.name, scanner))?;
+
+    if compiler.locals[slot].depth == -1 {
+        error(
+            name,
+            "Can't read local variable in its own initializer.",
+            scanner,
+            parser,
+        );
+    }
+    Some(slot as u8)
+}
+
+fn add_upvalue(
+    index: u8,
+    is_local: bool,
+    parser: &mut Parser,
+    scanner: &Scanner,
+    compiler: &mut Compiler,
+) -> u8 {
+    if let Some(existing) = compiler
+        .upvalues
+        .iter()
+        .position(|upvalue| upvalue.index == index && upvalue.is_local == is_local)
     {
-        if !parser.had_error {
-            disassemble_chunk(compiling_chunk, "code");
+        return existing as u8;
+    }
+
+    if compiler.upvalues.len() == UINT8_COUNT {
+        error(
+            &parser.previous.clone().unwrap(),
+            "Too many closure variables in function.",
+            scanner,
+            parser,
+        );
+        return 0;
+    }
+
+    compiler.upvalues.push(Upvalue { index, is_local });
+    compiler.function.upvalue_count = compiler.upvalues.len();
+    (compiler.upvalues.len() - 1) as u8
+}
+
+/// Looks `name` up in the enclosing functions, threading an upvalue through
+/// every function in between so each closure can find it at creation time.
+fn resolve_upvalue(
+    name: &Token,
+    parser: &mut Parser,
+    scanner: &Scanner,
+    compiler: &mut Compiler,
+) -> Option<u8> {
+    let enclosing = compiler.enclosing.as_deref_mut()?;
+
+    if let Some(local) = resolve_local(name, parser, scanner, enclosing) {
+        enclosing.locals[local as usize].is_captured = true;
+        return Some(add_upvalue(local, true, parser, scanner, compiler));
+    }
+
+    let upvalue = resolve_upvalue(name, parser, scanner, enclosing)?;
+    Some(add_upvalue(upvalue, false, parser, scanner, compiler))
+}
+
+fn mark_initialized(compiler: &mut Compiler) {
+    if compiler.scope_depth == 0 {
+        return;
+    }
+    if let Some(local) = compiler.locals.last_mut() {
+        local.depth = compiler.scope_depth;
+    }
+}
+
+fn define_variable(global: u8, parser: &mut Parser, compiler: &mut Compiler) {
+    if compiler.scope_depth > 0 {
+        mark_initialized(compiler);
+        return;
+    }
+
+    emit_bytes(OpCode::OP_DEFINE_GLOBAL as u8, global, parser, compiler);
+}
+
+fn statement(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    if match_token(TokenType::TOKEN_PRINT, parser, scanner) {
+        print_statement(parser, scanner, compiler);
+    } else if match_token(TokenType::TOKEN_FOR, parser, scanner) {
+        for_statement(parser, scanner, compiler);
+    } else if match_token(TokenType::TOKEN_IF, parser, scanner) {
+        if_statement(parser, scanner, compiler);
+    } else if match_token(TokenType::TOKEN_RETURN, parser, scanner) {
+        return_statement(parser, scanner, compiler);
+    } else if match_token(TokenType::TOKEN_WHILE, parser, scanner) {
+        while_statement(parser, scanner, compiler);
+    } else if match_token(TokenType::TOKEN_LEFT_BRACE, parser, scanner) {
+        begin_scope(compiler);
+        block(parser, scanner, compiler);
+        end_scope(parser, compiler);
+    } else {
+        expression_statement(parser, scanner, compiler);
+    }
+}
+
+fn block(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    while !check(TokenType::TOKEN_RIGHT_BRACE, parser) && !check(TokenType::TOKEN_EOF, parser) {
+        declaration(parser, scanner, compiler);
+    }
+
+    consume(
+        TokenType::TOKEN_RIGHT_BRACE,
+        "Expect '}' after block.",
+        parser,
+        scanner,
+    );
+}
+
+fn begin_scope(compiler: &mut Compiler) {
+    compiler.scope_depth += 1;
+}
+
+fn end_scope(parser: &mut Parser, compiler: &mut Compiler) {
+    compiler.scope_depth -= 1;
+
+    while let Some(local) = compiler.locals.last() {
+        if local.depth <= compiler.scope_depth {
+            break;
         }
+
+        if local.is_captured {
+            emit_byte(OpCode::OP_CLOSE_UPVALUE as u8, parser, compiler);
+        } else {
+            emit_byte(OpCode::OP_POP as u8, parser, compiler);
+        }
+        compiler.locals.pop();
     }
 }
 
-fn emit_return(parser: &mut Parser, compiling_chunk: &mut Chunk) {
-    emit_byte(OpCode::OP_RETURN as u8, parser, compiling_chunk)
+fn if_statement(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    consume(
+        TokenType::TOKEN_LEFT_PAREN,
+        "Expect '(' after 'if'.",
+        parser,
+        scanner,
+    );
+    expression(parser, scanner, compiler);
+    consume(
+        TokenType::TOKEN_RIGHT_PAREN,
+        "Expect ')' after condition.",
+        parser,
+        scanner,
+    );
+
+    let then_jump = emit_jump(OpCode::OP_JUMP_IF_FALSE, parser, compiler);
+    emit_byte(OpCode::OP_POP as u8, parser, compiler);
+    statement(parser, scanner, compiler);
+
+    let else_jump = emit_jump(OpCode::OP_JUMP, parser, compiler);
+
+    patch_jump(then_jump, parser, scanner, compiler);
+    emit_byte(OpCode::OP_POP as u8, parser, compiler);
+
+    if match_token(TokenType::TOKEN_ELSE, parser, scanner) {
+        statement(parser, scanner, compiler);
+    }
+    patch_jump(else_jump, parser, scanner, compiler);
 }
 
-fn emit_bytes(byte_1: u8, byte_2: u8, parser: &Parser, compiling_chunk: &mut Chunk) {
-    emit_byte(byte_1, parser, compiling_chunk);
-    emit_byte(byte_2, parser, compiling_chunk);
+fn return_statement(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    if compiler.function_type == FunctionType::TYPE_SCRIPT {
+        error(
+            &parser.previous.clone().unwrap(),
+            "Can't return from top-level code.",
+            scanner,
+            parser,
+        );
+    }
+
+    if match_token(TokenType::TOKEN_SEMICOLON, parser, scanner) {
+        emit_return(parser, compiler);
+    } else {
+        if compiler.function_type == FunctionType::TYPE_INITIALIZER {
+            error(
+                &parser.previous.clone().unwrap(),
+                "Can't return a value from an initializer.",
+                scanner,
+                parser,
+            );
+        }
+
+        expression(parser, scanner, compiler);
+        consume(
+            TokenType::TOKEN_SEMICOLON,
+            "Expect ';' after return value.",
+            parser,
+            scanner,
+        );
+        emit_byte(OpCode::OP_RETURN as u8, parser, compiler);
+    }
 }
 
-fn expression(parser: &mut Parser, scanner: &mut Scanner, compiling_chunk: &mut Chunk) {
-    parse_precedence(
-        Precedence::PREC_ASSIGNMENT,
+fn while_statement(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    let loop_start = current_chunk(compiler).code.len();
+    consume(
+        TokenType::TOKEN_LEFT_PAREN,
+        "Expect '(' after 'while'.",
+        parser,
+        scanner,
+    );
+    expression(parser, scanner, compiler);
+    consume(
+        TokenType::TOKEN_RIGHT_PAREN,
+        "Expect ')' after condition.",
+        parser,
+        scanner,
+    );
+
+    let exit_jump = emit_jump(OpCode::OP_JUMP_IF_FALSE, parser, compiler);
+    emit_byte(OpCode::OP_POP as u8, parser, compiler);
+    statement(parser, scanner, compiler);
+    emit_loop(loop_start, parser, scanner, compiler);
+
+    patch_jump(exit_jump, parser, scanner, compiler);
+    emit_byte(OpCode::OP_POP as u8, parser, compiler);
+}
+
+/// Desugars `for (init; condition; increment) body` into a scoped `while`
+/// loop. The increment is compiled before the body, so the body jumps back
+/// to it and it then loops to the condition.
+fn for_statement(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    begin_scope(compiler);
+    consume(
+        TokenType::TOKEN_LEFT_PAREN,
+        "Expect '(' after 'for'.",
+        parser,
+        scanner,
+    );
+    if match_token(TokenType::TOKEN_SEMICOLON, parser, scanner) {
+        // No initializer.
+    } else if match_token(TokenType::TOKEN_VAR, parser, scanner) {
+        var_declaration(parser, scanner, compiler);
+    } else {
+        expression_statement(parser, scanner, compiler);
+    }
+
+    let mut loop_start = current_chunk(compiler).code.len();
+    let mut exit_jump = None;
+    if !match_token(TokenType::TOKEN_SEMICOLON, parser, scanner) {
+        expression(parser, scanner, compiler);
+        consume(
+            TokenType::TOKEN_SEMICOLON,
+            "Expect ';' after loop condition.",
+            parser,
+            scanner,
+        );
+
+        exit_jump = Some(emit_jump(OpCode::OP_JUMP_IF_FALSE, parser, compiler));
+        emit_byte(OpCode::OP_POP as u8, parser, compiler);
+    }
+
+    if !match_token(TokenType::TOKEN_RIGHT_PAREN, parser, scanner) {
+        let body_jump = emit_jump(OpCode::OP_JUMP, parser, compiler);
+        let increment_start = current_chunk(compiler).code.len();
+        expression(parser, scanner, compiler);
+        emit_byte(OpCode::OP_POP as u8, parser, compiler);
+        consume(
+            TokenType::TOKEN_RIGHT_PAREN,
+            "Expect ')' after for clauses.",
+            parser,
+            scanner,
+        );
+
+        emit_loop(loop_start, parser, scanner, compiler);
+        loop_start = increment_start;
+        patch_jump(body_jump, parser, scanner, compiler);
+    }
+
+    statement(parser, scanner, compiler);
+    emit_loop(loop_start, parser, scanner, compiler);
+
+    if let Some(exit_jump) = exit_jump {
+        patch_jump(exit_jump, parser, scanner, compiler);
+        emit_byte(OpCode::OP_POP as u8, parser, compiler);
+    }
+
+    end_scope(parser, compiler);
+}
+
+fn print_statement(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    expression(parser, scanner, compiler);
+    consume(
+        TokenType::TOKEN_SEMICOLON,
+        "Expect ';' after value.",
+        parser,
+        scanner,
+    );
+    emit_byte(OpCode::OP_PRINT as u8, parser, compiler);
+}
+
+fn expression_statement(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) {
+    expression(parser, scanner, compiler);
+    if parser.repl
+        && compiler.function_type == FunctionType::TYPE_SCRIPT
+        && compiler.scope_depth == 0
+        && check(TokenType::TOKEN_EOF, parser)
+    {
+        emit_byte(OpCode::OP_PRINT as u8, parser, compiler);
+        return;
+    }
+
+    consume(
+        TokenType::TOKEN_SEMICOLON,
+        "Expect ';' after expression.",
         parser,
         scanner,
-        compiling_chunk,
     );
+    emit_byte(OpCode::OP_POP as u8, parser, compiler);
+}
+
+/// Skips tokens until a statement boundary so one syntax error does not
+/// cascade into a flood of follow-up errors.
+fn synchronize(parser: &mut Parser, scanner: &mut Scanner) {
+    parser.panic_mode = false;
+
+    while parser.current.as_ref().unwrap().typo != TokenType::TOKEN_EOF {
+        if parser.previous.as_ref().unwrap().typo == TokenType::TOKEN_SEMICOLON {
+            return;
+        }
+        match parser.current.as_ref().unwrap().typo {
+            TokenType::TOKEN_CLASS
+            | TokenType::TOKEN_FUN
+            | TokenType::TOKEN_VAR
+            | TokenType::TOKEN_FOR
+            | TokenType::TOKEN_IF
+            | TokenType::TOKEN_WHILE
+            | TokenType::TOKEN_PRINT
+            | TokenType::TOKEN_RETURN => return,
+            _ => {}
+        }
+
+        parser.advance(scanner);
+    }
+}
+
+fn number(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler, _can_assign: bool) {
+    let value = Value::Number(
+        scanner.source[parser.previous.as_ref().unwrap().start
+            ..parser.previous.as_ref().unwrap().start + parser.previous.as_ref().unwrap().length]
+            .parse()
+            .unwrap(),
+    );
+    emit_constant(value, parser, compiler, scanner);
 }
 
-fn number(parser: &mut Parser, scanner: &mut Scanner, compiling_chunk: &mut Chunk) {
-    let value: Value = (scanner.source[parser.previous.as_ref().unwrap().start
-        ..parser.previous.as_ref().unwrap().start + parser.previous.as_ref().unwrap().length])
-        .parse()
-        .unwrap();
-    emit_constant(value, parser, compiling_chunk, scanner);
+fn literal(
+    parser: &mut Parser,
+    _scanner: &mut Scanner,
+    compiler: &mut Compiler,
+    _can_assign: bool,
+) {
+    match parser.previous.as_ref().unwrap().typo {
+        TokenType::TOKEN_FALSE => emit_byte(OpCode::OP_FALSE as u8, parser, compiler),
+        TokenType::TOKEN_NIL => emit_byte(OpCode::OP_NIL as u8, parser, compiler),
+        TokenType::TOKEN_TRUE => emit_byte(OpCode::OP_TRUE as u8, parser, compiler),
+        _ => {}
+    }
+}
+
+fn string(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler, _can_assign: bool) {
+    let token = parser.previous.as_ref().unwrap();
+    let chars = &scanner.source[token.start + 1..token.start + token.length - 1];
+    let string = parser.vm.copy_string(chars);
+    emit_constant(Value::Obj(Obj::String(string)), parser, compiler, scanner);
+}
+
+fn variable(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler, can_assign: bool) {
+    let name = parser.previous.clone().unwrap();
+    named_variable(&name, parser, scanner, compiler, can_assign);
 }
 
-fn grouping(parser: &mut Parser, scanner: &mut Scanner, compiling_chunk: &mut Chunk) {
-    expression(parser, scanner, compiling_chunk);
+fn named_variable(
+    name: &Token,
+    parser: &mut Parser,
+    scanner: &mut Scanner,
+    compiler: &mut Compiler,
+    can_assign: bool,
+) {
+    let (get_op, set_op, arg) = if let Some(slot) = resolve_local(name, parser, scanner, compiler) {
+        (OpCode::OP_GET_LOCAL, OpCode::OP_SET_LOCAL, slot)
+    } else if let Some(upvalue) = resolve_upvalue(name, parser, scanner, compiler) {
+        (OpCode::OP_GET_UPVALUE, OpCode::OP_SET_UPVALUE, upvalue)
+    } else {
+        (
+            OpCode::OP_GET_GLOBAL,
+            OpCode::OP_SET_GLOBAL,
+            identifier_constant(name, parser, scanner, compiler),
+        )
+    };
+
+    if can_assign && match_token(TokenType::TOKEN_EQUAL, parser, scanner) {
+        expression(parser, scanner, compiler);
+        emit_bytes(set_op as u8, arg, parser, compiler);
+    } else {
+        emit_bytes(get_op as u8, arg, parser, compiler);
+    }
+}
+
+fn super_(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler, _can_assign: bool) {
+    match &parser.class_compiler {
+        None => error(
+            &parser.previous.clone().unwrap(),
+            "Can't use 'super' outside of a class.",
+            scanner,
+            parser,
+        ),
+        Some(class_compiler) if !class_compiler.has_superclass => error(
+            &parser.previous.clone().unwrap(),
+            "Can't use 'super' in a class with no superclass.",
+            scanner,
+            parser,
+        ),
+        Some(_) => {}
+    }
+
+    consume(
+        TokenType::TOKEN_DOT,
+        "Expect '.' after 'super'.",
+        parser,
+        scanner,
+    );
+    consume(
+        TokenType::TOKEN_IDENTIFIER,
+        "Expect superclass method name.",
+        parser,
+        scanner,
+    );
+    let name = parser.previous.clone().unwrap();
+    let name = identifier_constant(&name, parser, scanner, compiler);
+
+    let this = synthetic_token(TokenType::TOKEN_THIS);
+    let super_ = synthetic_token(TokenType::TOKEN_SUPER);
+    named_variable(&this, parser, scanner, compiler, false);
+    if match_token(TokenType::TOKEN_LEFT_PAREN, parser, scanner) {
+        let arg_count = argument_list(parser, scanner, compiler);
+        named_variable(&super_, parser, scanner, compiler, false);
+        emit_bytes(OpCode::OP_SUPER_INVOKE as u8, name, parser, compiler);
+        emit_byte(arg_count, parser, compiler);
+    } else {
+        named_variable(&super_, parser, scanner, compiler, false);
+        emit_bytes(OpCode::OP_GET_SUPER as u8, name, parser, compiler);
+    }
+}
+
+fn this_(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler, _can_assign: bool) {
+    if parser.class_compiler.is_none() {
+        error(
+            &parser.previous.clone().unwrap(),
+            "Can't use 'this' outside of a class.",
+            scanner,
+            parser,
+        );
+        return;
+    }
+
+    variable(parser, scanner, compiler, false);
+}
+
+fn call(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler, _can_assign: bool) {
+    let arg_count = argument_list(parser, scanner, compiler);
+    emit_bytes(OpCode::OP_CALL as u8, arg_count, parser, compiler);
+}
+
+fn dot(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler, can_assign: bool) {
+    consume(
+        TokenType::TOKEN_IDENTIFIER,
+        "Expect property name after '.'.",
+        parser,
+        scanner,
+    );
+    let name = parser.previous.clone().unwrap();
+    let name = identifier_constant(&name, parser, scanner, compiler);
+
+    if can_assign && match_token(TokenType::TOKEN_EQUAL, parser, scanner) {
+        expression(parser, scanner, compiler);
+        emit_bytes(OpCode::OP_SET_PROPERTY as u8, name, parser, compiler);
+    } else if match_token(TokenType::TOKEN_LEFT_PAREN, parser, scanner) {
+        let arg_count = argument_list(parser, scanner, compiler);
+        emit_bytes(OpCode::OP_INVOKE as u8, name, parser, compiler);
+        emit_byte(arg_count, parser, compiler);
+    } else {
+        emit_bytes(OpCode::OP_GET_PROPERTY as u8, name, parser, compiler);
+    }
+}
+
+fn argument_list(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler) -> u8 {
+    let mut arg_count: usize = 0;
+    if !check(TokenType::TOKEN_RIGHT_PAREN, parser) {
+        loop {
+            expression(parser, scanner, compiler);
+            if arg_count == 255 {
+                error(
+                    &parser.previous.clone().unwrap(),
+                    "Can't have more than 255 arguments.",
+                    scanner,
+                    parser,
+                );
+            }
+            arg_count += 1;
+
+            if !match_token(TokenType::TOKEN_COMMA, parser, scanner) {
+                break;
+            }
+        }
+    }
+    consume(
+        TokenType::TOKEN_RIGHT_PAREN,
+        "Expect ')' after arguments.",
+        parser,
+        scanner,
+    );
+    arg_count as u8
+}
+
+fn and(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler, _can_assign: bool) {
+    let end_jump = emit_jump(OpCode::OP_JUMP_IF_FALSE, parser, compiler);
+
+    emit_byte(OpCode::OP_POP as u8, parser, compiler);
+    parse_precedence(Precedence::PREC_AND, parser, scanner, compiler);
+
+    patch_jump(end_jump, parser, scanner, compiler);
+}
+
+fn or(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler, _can_assign: bool) {
+    let else_jump = emit_jump(OpCode::OP_JUMP_IF_FALSE, parser, compiler);
+    let end_jump = emit_jump(OpCode::OP_JUMP, parser, compiler);
+
+    patch_jump(else_jump, parser, scanner, compiler);
+    emit_byte(OpCode::OP_POP as u8, parser, compiler);
+
+    parse_precedence(Precedence::PREC_OR, parser, scanner, compiler);
+    patch_jump(end_jump, parser, scanner, compiler);
+}
+
+fn grouping(
+    parser: &mut Parser,
+    scanner: &mut Scanner,
+    compiler: &mut Compiler,
+    _can_assign: bool,
+) {
+    expression(parser, scanner, compiler);
     consume(
         TokenType::TOKEN_RIGHT_PAREN,
         "Expect ')' after expression.",
@@ -246,18 +1233,19 @@ fn grouping(parser: &mut Parser, scanner: &mut Scanner, compiling_chunk: &mut Ch
     );
 }
 
-fn unary(parser: &mut Parser, scanner: &mut Scanner, compiling_chunk: &mut Chunk) {
+fn unary(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler, _can_assign: bool) {
     let operator_type = parser.previous.as_ref().unwrap().typo;
 
-    parse_precedence(Precedence::PREC_UNARY, parser, scanner, compiling_chunk);
+    parse_precedence(Precedence::PREC_UNARY, parser, scanner, compiler);
 
     match operator_type {
-        TokenType::TOKEN_MINUS => emit_byte(OpCode::OP_NEGATE as u8, parser, compiling_chunk),
+        TokenType::TOKEN_BANG => emit_byte(OpCode::OP_NOT as u8, parser, compiler),
+        TokenType::TOKEN_MINUS => emit_byte(OpCode::OP_NEGATE as u8, parser, compiler),
         _ => {}
     }
 }
 
-fn binary(parser: &mut Parser, scanner: &mut Scanner, compiling_chunk: &mut Chunk) {
+fn binary(parser: &mut Parser, scanner: &mut Scanner, compiler: &mut Compiler, _can_assign: bool) {
     let operator_type = parser.previous.as_ref().unwrap().typo;
 
     let rule = get_rule(operator_type);
@@ -266,14 +1254,35 @@ fn binary(parser: &mut Parser, scanner: &mut Scanner, compiling_chunk: &mut Chun
         Precedence::from(rule.precedence as u8 + 1),
         parser,
         scanner,
-        compiling_chunk,
+        compiler,
     );
 
     match operator_type {
-        TokenType::TOKEN_PLUS => emit_byte(OpCode::OP_ADD as u8, parser, compiling_chunk),
-        TokenType::TOKEN_MINUS => emit_byte(OpCode::OP_SUBTRACT as u8, parser, compiling_chunk),
-        TokenType::TOKEN_STAR => emit_byte(OpCode::OP_MULTIPLY as u8, parser, compiling_chunk),
-        TokenType::TOKEN_SLASH => emit_byte(OpCode::OP_DIVIDE as u8, parser, compiling_chunk),
+        TokenType::TOKEN_BANG_EQUAL => emit_bytes(
+            OpCode::OP_EQUAL as u8,
+            OpCode::OP_NOT as u8,
+            parser,
+            compiler,
+        ),
+        TokenType::TOKEN_EQUAL_EQUAL => emit_byte(OpCode::OP_EQUAL as u8, parser, compiler),
+        TokenType::TOKEN_GREATER => emit_byte(OpCode::OP_GREATER as u8, parser, compiler),
+        TokenType::TOKEN_GREATER_EQUAL => emit_bytes(
+            OpCode::OP_LESS as u8,
+            OpCode::OP_NOT as u8,
+            parser,
+            compiler,
+        ),
+        TokenType::TOKEN_LESS => emit_byte(OpCode::OP_LESS as u8, parser, compiler),
+        TokenType::TOKEN_LESS_EQUAL => emit_bytes(
+            OpCode::OP_GREATER as u8,
+            OpCode::OP_NOT as u8,
+            parser,
+            compiler,
+        ),
+        TokenType::TOKEN_PLUS => emit_byte(OpCode::OP_ADD as u8, parser, compiler),
+        TokenType::TOKEN_MINUS => emit_byte(OpCode::OP_SUBTRACT as u8, parser, compiler),
+        TokenType::TOKEN_STAR => emit_byte(OpCode::OP_MULTIPLY as u8, parser, compiler),
+        TokenType::TOKEN_SLASH => emit_byte(OpCode::OP_DIVIDE as u8, parser, compiler),
         _ => {}
     }
 }
@@ -291,10 +1300,8 @@ fn parse_precedence(
     precedence: Precedence,
     parser: &mut Parser,
     scanner: &mut Scanner,
-    chunk: &mut Chunk,
+    compiler: &mut Compiler,
 ) {
-    let mut precedence = precedence;
-
     parser.advance(scanner);
 
     let prefix_rule = get_rule(parser.previous.as_ref().unwrap().typo).prefix;
@@ -306,7 +1313,8 @@ fn parse_precedence(
             parser,
         ),
         Some(prefix) => {
-            prefix(parser, scanner, chunk);
+            let can_assign = precedence.clone() as u8 <= Precedence::PREC_ASSIGNMENT as u8;
+            prefix(parser, scanner, compiler, can_assign);
             while (precedence.clone() as u8)
                 <= (get_rule(parser.current.as_ref().unwrap().typo).precedence as u8)
             {
@@ -315,32 +1323,36 @@ fn parse_precedence(
                     .infix
                     .unwrap();
 
-                infix(parser, scanner, chunk);
+                infix(parser, scanner, compiler, can_assign);
+            }
+
+            if can_assign && match_token(TokenType::TOKEN_EQUAL, parser, scanner) {
+                error(
+                    &parser.previous.clone().unwrap(),
+                    "Invalid assignment target.",
+                    scanner,
+                    parser,
+                );
             }
         }
     }
 }
 
-fn emit_constant(
-    value: Value,
-    parser: &mut Parser,
-    compiling_chunk: &mut Chunk,
-    scanner: &Scanner,
-) {
+fn emit_constant(value: Value, parser: &mut Parser, compiler: &mut Compiler, scanner: &Scanner) {
     emit_bytes(
         OpCode::OP_CONSTANT as u8,
-        make_constant(value, compiling_chunk, parser, scanner),
+        make_constant(value, compiler, parser, scanner),
         parser,
-        compiling_chunk,
+        compiler,
     )
 }
 fn make_constant(
     value: Value,
-    current_chunk: &mut Chunk,
+    compiler: &mut Compiler,
     parser: &mut Parser,
     scanner: &Scanner,
 ) -> u8 {
-    let constant = current_chunk.add_constant(value);
+    let constant = current_chunk(compiler).add_constant(value);
 
     if constant == u8::MAX {
         error(
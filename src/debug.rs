@@ -1,6 +1,7 @@
 use crate::{
     chunk::{Chunk, OpCode},
-    value::print_value,
+    object::{Obj, ObjFunction},
+    value::{print_value, Value},
 };
 
 pub fn disassemble_chunk(chunk: &Chunk, name: &str) {
@@ -11,6 +12,17 @@ pub fn disassemble_chunk(chunk: &Chunk, name: &str) {
     }
 }
 
+/// Disassembles `function` followed by every function nested in it.
+pub fn disassemble_function(function: &ObjFunction) {
+    disassemble_chunk(&function.chunk, &function.to_string());
+    for constant in &function.chunk.constants.values {
+        if let Value::Obj(Obj::Function(nested)) = constant {
+            println!();
+            disassemble_function(nested);
+        }
+    }
+}
+
 pub fn disassemble_instruction(chunk: &Chunk, offset: &usize) -> usize {
     print!("{:#04} ", offset);
 
@@ -23,8 +35,38 @@ pub fn disassemble_instruction(chunk: &Chunk, offset: &usize) -> usize {
     if let Ok(chunk_instruction) = chunk.code[*offset].try_into() {
         match chunk_instruction {
             OpCode::OP_CONSTANT => constant_instruction("OP_CONSTANT", chunk, offset),
+            OpCode::OP_NIL => simple_instruction("OP_NIL", offset),
+            OpCode::OP_TRUE => simple_instruction("OP_TRUE", offset),
+            OpCode::OP_FALSE => simple_instruction("OP_FALSE", offset),
+            OpCode::OP_EQUAL => simple_instruction("OP_EQUAL", offset),
+            OpCode::OP_GREATER => simple_instruction("OP_GREATER", offset),
+            OpCode::OP_LESS => simple_instruction("OP_LESS", offset),
+            OpCode::OP_NOT => simple_instruction("OP_NOT", offset),
+            OpCode::OP_POP => simple_instruction("OP_POP", offset),
+            OpCode::OP_PRINT => simple_instruction("OP_PRINT", offset),
+            OpCode::OP_JUMP => jump_instruction("OP_JUMP", 1, chunk, offset),
+            OpCode::OP_JUMP_IF_FALSE => jump_instruction("OP_JUMP_IF_FALSE", 1, chunk, offset),
+            OpCode::OP_LOOP => jump_instruction("OP_LOOP", -1, chunk, offset),
+            OpCode::OP_CALL => byte_instruction("OP_CALL", chunk, offset),
+            OpCode::OP_INVOKE => invoke_instruction("OP_INVOKE", chunk, offset),
+            OpCode::OP_SUPER_INVOKE => invoke_instruction("OP_SUPER_INVOKE", chunk, offset),
+            OpCode::OP_GET_UPVALUE => byte_instruction("OP_GET_UPVALUE", chunk, offset),
+            OpCode::OP_SET_UPVALUE => byte_instruction("OP_SET_UPVALUE", chunk, offset),
+            OpCode::OP_GET_PROPERTY => constant_instruction("OP_GET_PROPERTY", chunk, offset),
+            OpCode::OP_SET_PROPERTY => constant_instruction("OP_SET_PROPERTY", chunk, offset),
+            OpCode::OP_GET_SUPER => constant_instruction("OP_GET_SUPER", chunk, offset),
+            OpCode::OP_CLOSURE => closure_instruction(chunk, offset),
+            OpCode::OP_CLOSE_UPVALUE => simple_instruction("OP_CLOSE_UPVALUE", offset),
+            OpCode::OP_GET_LOCAL => byte_instruction("OP_GET_LOCAL", chunk, offset),
+            OpCode::OP_SET_LOCAL => byte_instruction("OP_SET_LOCAL", chunk, offset),
+            OpCode::OP_GET_GLOBAL => constant_instruction("OP_GET_GLOBAL", chunk, offset),
+            OpCode::OP_DEFINE_GLOBAL => constant_instruction("OP_DEFINE_GLOBAL", chunk, offset),
+            OpCode::OP_SET_GLOBAL => constant_instruction("OP_SET_GLOBAL", chunk, offset),
             OpCode::OP_NEGATE => simple_instruction("OP_NEGATE", offset),
             OpCode::OP_RETURN => simple_instruction("OP_RETURN", offset),
+            OpCode::OP_CLASS => constant_instruction("OP_CLASS", chunk, offset),
+            OpCode::OP_INHERIT => simple_instruction("OP_INHERIT", offset),
+            OpCode::OP_METHOD => constant_instruction("OP_METHOD", chunk, offset),
             OpCode::OP_ADD => simple_instruction("OP_ADD", offset),
             OpCode::OP_DIVIDE => simple_instruction("OP_DIVIDE", offset),
             OpCode::OP_MULTIPLY => simple_instruction("OP_MULTIPLY", offset),
@@ -41,10 +83,56 @@ fn simple_instruction(name: &str, offset: &usize) -> usize {
     offset + 1
 }
 
+fn byte_instruction(name: &str, chunk: &Chunk, offset: &usize) -> usize {
+    let slot = chunk.code[*offset + 1];
+    println!("{:<16} {:>4}", name, slot);
+    *offset + 2
+}
+
+fn jump_instruction(name: &str, sign: isize, chunk: &Chunk, offset: &usize) -> usize {
+    let jump = u16::from_be_bytes([chunk.code[*offset + 1], chunk.code[*offset + 2]]);
+    let target = *offset as isize + 3 + sign * jump as isize;
+    println!("{:<16} {:>4} -> {}", name, offset, target);
+    *offset + 3
+}
+
 fn constant_instruction(name: &str, chunk: &Chunk, offset: &usize) -> usize {
     let constant = chunk.code[*offset + 1];
-    print!("{:<16} {:>4} '", name, offset);
+    print!("{:<16} {:>4} '", name, constant);
     print_value(&chunk.constants.values[constant as usize]);
     println!("'");
     *offset + 2
 }
+
+fn invoke_instruction(name: &str, chunk: &Chunk, offset: &usize) -> usize {
+    let constant = chunk.code[*offset + 1];
+    let arg_count = chunk.code[*offset + 2];
+    print!("{:<16} ({} args) {:>4} '", name, arg_count, constant);
+    print_value(&chunk.constants.values[constant as usize]);
+    println!("'");
+    *offset + 3
+}
+
+fn closure_instruction(chunk: &Chunk, offset: &usize) -> usize {
+    let constant = chunk.code[*offset + 1];
+    let value = &chunk.constants.values[constant as usize];
+    print!("{:<16} {:>4} ", "OP_CLOSURE", constant);
+    print_value(value);
+    println!();
+
+    let mut offset = *offset + 2;
+    if let Value::Obj(Obj::Function(function)) = value {
+        for _ in 0..function.upvalue_count {
+            let is_local = chunk.code[offset];
+            let index = chunk.code[offset + 1];
+            println!(
+                "{:04}    |                     {} {}",
+                offset,
+                if is_local == 1 { "local" } else { "upvalue" },
+                index
+            );
+            offset += 2;
+        }
+    }
+    offset
+}
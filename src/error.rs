@@ -0,0 +1,125 @@
+use std::{error::Error, fmt, ops::Range};
+
+/// A single problem found while compiling a script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub line: usize,
+    /// One-based column of the first character of the offending token,
+    /// counted in characters rather than bytes.
+    pub column: usize,
+    /// Byte range of the offending token in the source.
+    pub span: Range<usize>,
+    pub message: String,
+    /// Source text of the offending token. `None` when the error was
+    /// reported by the scanner or at the end of the input.
+    pub lexeme: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[line {}] Error", self.line)?;
+        match &self.lexeme {
+            Some(lexeme) => write!(f, " at '{}'", lexeme)?,
+            None if self.span.is_empty() => write!(f, " at end")?,
+            None => {}
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Every diagnostic reported while compiling a script. The compiler
+/// recovers after an error, so there is at least one and possibly several.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileError {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for CompileError {}
+
+/// A call that was active when a runtime error occurred.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    pub line: usize,
+    /// `None` for the top-level script.
+    pub function: Option<String>,
+}
+
+impl fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.function {
+            Some(name) => write!(f, "[line {}] in {}()", self.line, name),
+            None => write!(f, "[line {}] in script", self.line),
+        }
+    }
+}
+
+/// An error raised while running a script, either by the VM itself or by a
+/// native function. Natives only fill in the message; the VM attaches the
+/// trace of the calls that led to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+    pub trace: Vec<TraceFrame>,
+}
+
+impl RuntimeError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            trace: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        for frame in &self.trace {
+            write!(f, "\n{}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for RuntimeError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterpretError {
+    Compile(CompileError),
+    Runtime(RuntimeError),
+}
+
+impl fmt::Display for InterpretError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InterpretError::Compile(error) => write!(f, "{}", error),
+            InterpretError::Runtime(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for InterpretError {}
+
+impl From<CompileError> for InterpretError {
+    fn from(error: CompileError) -> Self {
+        InterpretError::Compile(error)
+    }
+}
+
+impl From<RuntimeError> for InterpretError {
+    fn from(error: RuntimeError) -> Self {
+        InterpretError::Runtime(error)
+    }
+}
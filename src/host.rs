@@ -0,0 +1,95 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    rc::{Rc, Weak},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::object::Obj;
+
+/// A value as the embedding program sees it. Unlike the VM's own values it
+/// never points into the heap: strings are copied out, and any other object
+/// is reached through a [`Handle`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Object(Handle),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(boolean) => write!(f, "{}", boolean),
+            Value::Number(number) => write!(f, "{}", number),
+            Value::String(chars) => write!(f, "{}", chars),
+            Value::Object(handle) => write!(f, "{}", handle.0.description),
+        }
+    }
+}
+
+/// Objects the embedder holds handles to, by handle id. The collector
+/// treats them as roots.
+pub(crate) type Handles = Rc<RefCell<HashMap<u64, Obj>>>;
+
+/// Ids are unique across VMs, so a handle never finds an object in a VM
+/// other than the one that created it.
+static NEXT_HANDLE: AtomicU64 = AtomicU64::new(0);
+
+/// A function, class, instance or other heap object held by the embedder.
+/// The VM that created the handle keeps the object alive for as long as any
+/// clone of it exists, and only that VM accepts it back.
+#[derive(Clone)]
+pub struct Handle(Rc<Rooted>);
+
+struct Rooted {
+    id: u64,
+    /// Only used to tell objects apart, never followed.
+    obj: Obj,
+    /// What printing the object showed when the handle was made.
+    description: String,
+    handles: Weak<RefCell<HashMap<u64, Obj>>>,
+}
+
+impl Handle {
+    pub(crate) fn new(handles: &Handles, obj: Obj) -> Self {
+        let id = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+        handles.borrow_mut().insert(id, obj);
+        Handle(Rc::new(Rooted {
+            id,
+            obj,
+            description: obj.to_string(),
+            handles: Rc::downgrade(handles),
+        }))
+    }
+
+    /// The object, if `handles` belongs to the VM that created the handle.
+    pub(crate) fn get(&self, handles: &Handles) -> Option<Obj> {
+        handles.borrow().get(&self.0.id).copied()
+    }
+}
+
+impl PartialEq for Handle {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.obj == other.0.obj
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Handle").field(&self.0.description).finish()
+    }
+}
+
+impl Drop for Rooted {
+    fn drop(&mut self) {
+        // The VM may already be gone, taking its objects with it.
+        if let Some(handles) = self.handles.upgrade() {
+            handles.borrow_mut().remove(&self.id);
+        }
+    }
+}
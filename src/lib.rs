@@ -0,0 +1,36 @@
+//! roxty is a bytecode virtual machine for the Lox language.
+//!
+//! A [`VM`] owns its heap and globals, so it can be kept around and fed
+//! several scripts in turn. Host functions are exposed to scripts with
+//! [`VM::define_native`].
+//!
+//! The embedder never sees pointers into the VM's heap. A [`Value`] holds
+//! strings by copy and any other object through a [`Handle`], which keeps
+//! the object alive while any clone of it exists and is only accepted back
+//! by the VM that created it.
+//!
+//! With the `tree-walk` feature (on by default) the older tree-walk
+//! interpreter is available as [`tree_walk`], sharing the same language.
+
+mod chunk;
+mod common;
+mod compiler;
+mod debug;
+mod error;
+mod host;
+mod memory;
+mod object;
+mod scanner;
+mod trace;
+#[cfg(feature = "tree-walk")]
+#[path = "tree-walk/mod.rs"]
+pub mod tree_walk;
+mod value;
+mod vm;
+
+pub use error::{CompileError, Diagnostic, InterpretError, RuntimeError, TraceFrame};
+pub use host::{Handle, Value};
+pub use memory::HeapStats;
+pub use object::NativeFn;
+pub use trace::{Frame, StdoutTracer, Tracer};
+pub use vm::VM;
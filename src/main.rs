@@ -1,221 +1,317 @@
-// use std::{
-//     collections::{HashMap, VecDeque},
-//     env, fmt,
-//     io::Write,
-// };
-// mod ast;
-// mod interpreter;
-// mod parser;
-// mod scanner;
-// mod token;
-// use ast::{LoxVal, Stmt};
-// use parser::parse;
-// use scanner::Scanner;
-
-// #[derive(Debug, Clone)]
-// struct ArgsQuantityError;
-
-// #[derive(Clone, Debug)]
-// struct Enviroments {
-//     enclosing: Option<Box<Enviroments>>,
-//     map: HashMap<String, LoxVal>,
-// }
-// impl Enviroments {
-//     fn get(&self, id: &str) -> &LoxVal {
-//         if self.map.contains_key(id) {
-//             return self.map.get(id).unwrap();
-//         }
-
-//         match &self.enclosing {
-//             Some(enc) => enc.get(id),
-//             None => {
-//                 panic!("HEY NO VARI IN ENVIROMENT")
-//             }
-//         }
-//     }
-//     fn define(&mut self, key: String, value: LoxVal) {
-//         self.map.insert(key, value);
-//     }
-
-//     fn assign(&mut self, key: String, value: LoxVal) {
-//         if self.map.contains_key(&key) {
-//             self.map.insert(key, value);
-//             return;
-//         }
-
-//         match &mut self.enclosing {
-//             Some(enc) => enc.assign(key, value),
-//             None => {
-//                 panic!("HEY NO VARI IN ENVIROMENT")
-//             }
-//         }
-//     }
-// }
-
-// impl fmt::Display for ArgsQuantityError {
-//     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-//         write!(f, "invalid first item to double")
-//     }
-// }
-
-// fn main() -> Result<(), ArgsQuantityError> {
-//     let args: Vec<String> = env::args().collect();
-
-//     match args.len() {
-//         2 => {
-//             println!("run File {:?}", args);
-//             let file_path = &args[1];
-//             run_file(file_path).unwrap();
-//             Ok(())
-//         }
-//         1 => {
-//             run_prompt();
-//             Ok(())
-//         }
-//         _ => {
-//             eprintln!("Usage roxty [script]");
-//             Err(ArgsQuantityError)
-//         }
-//     }
-// }
-
-// fn run_file(path: &str) -> Result<(), (usize, String)> {
-//     let mut enviroments: HashMap<String, LoxVal> = HashMap::new();
-//     let mut env = Enviroments {
-//         enclosing: None,
-//         map: enviroments.clone(),
-//     };
-
-//     match std::fs::read_to_string(path) {
-//         Ok(file) => match run(&file, &mut env) {
-//             Ok(_) => {
-//                 println!("dentro de ok");
-//                 Ok(())
-//             }
-//             Err(err) => {
-//                 println!("dentro de err");
-
-//                 error(err.0, err.1.clone());
-//                 Err(err)
-//             }
-//         },
-//         Err(_error) => panic!("File Does not exists"),
-//     }
-// }
-// fn run_prompt() {
-//     let mut enviroments: HashMap<String, LoxVal> = HashMap::new();
-
-//     let mut env = Enviroments {
-//         enclosing: None,
-//         map: enviroments.clone(),
-//     };
-
-//     loop {
-//         let mut line = String::new();
-//         print!("> ");
-//         std::io::stdout().flush().unwrap();
-
-//         let check = std::io::stdin()
-//             .read_line(&mut line)
-//             .expect("Failed to read line");
-//         if check == 0 {
-//             println!();
-//             break;
-//         }
-
-//         match run(&line, &mut env) {
-//             Ok(_) => {}
-//             Err(err) => error(err.0, err.1),
-//         }
-//     }
-// }
-
-// fn run(source: &str, enviroments: &mut Enviroments) -> Result<(), (usize, String)> {
-//     let mut scanner = Scanner::new(source);
-
-//     let tokens = scanner.scan_tokens()?;
-
-//     let all_ast = parse(tokens, 0);
-
-//     // let val = ast.interpret();
-//     // println!("{:?}", val);
-//     for stmt in all_ast.iter() {
-//         stmt.eval(enviroments);
-//     }
-//     Ok(())
-// }
-
-// fn error(line: usize, message: String) {
-//     report(line, "".to_string(), message);
-// }
-// fn report(line: usize, donde: String, message: String) {
-//     eprintln!("[line {}] Error {}: {}", line, donde, message);
-// }
-mod chunk;
-mod common;
-mod compiler;
-mod debug;
-mod scanner;
-mod value;
-mod vm;
-
 use std::env;
 use std::io::Write;
 use std::process::ExitCode;
+#[cfg(feature = "tree-walk")]
+use std::{cell::RefCell, rc::Rc};
+
+#[cfg(feature = "tree-walk")]
+use roxty::tree_walk::{self, Enviroments, TreeWalkError};
+use roxty::{CompileError, InterpretError, StdoutTracer, VM};
+
+const USAGE: &str =
+    "Usage: roxty [--engine=vm|tree] [--disassemble] [--trace] [-e code | [--] script] [args...]";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Engine {
+    Vm,
+    Tree,
+}
+
+struct Options {
+    engine: Engine,
+    disassemble: bool,
+    trace: bool,
+    /// Source given with `-e`, run instead of a script file.
+    eval: Option<String>,
+    script: Option<String>,
+    /// Everything after the script (or after `-e code`), passed through to
+    /// the program. Options are not recognized there, so arguments may start
+    /// with `-`.
+    args: Vec<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        engine: Engine::Vm,
+        disassemble: false,
+        trace: false,
+        eval: None,
+        script: None,
+        args: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-e" => match args.next() {
+                Some(code) => {
+                    options.eval = Some(code);
+                    options.args.extend(args);
+                    break;
+                }
+                None => return Err("Option '-e' expects code to evaluate.".to_string()),
+            },
+            // Ends the options, so a script name may start with `-`.
+            "--" => {
+                options.script = args.next();
+                options.args.extend(args);
+                break;
+            }
+            "--disassemble" => options.disassemble = true,
+            "--trace" => options.trace = true,
+            "--engine=vm" => options.engine = Engine::Vm,
+            "--engine=tree" => options.engine = Engine::Tree,
+            _ if arg.starts_with('-') => return Err(format!("Unknown option '{}'.", arg)),
+            _ => {
+                options.script = Some(arg);
+                options.args.extend(args);
+                break;
+            }
+        }
+    }
 
-use chunk::Chunk;
-use vm::InterpretResultError;
-use vm::VM;
+    Ok(options)
+}
 
 fn main() -> ExitCode {
-    let mut vm = VM::new();
-    vm.init_vm();
-    let mut chunk = Chunk::init_chunk();
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("{}", USAGE);
+            return ExitCode::from(64);
+        }
+    };
 
-    let args: Vec<String> = env::args().collect();
-    if args.len() == 1 {
-        repl(&mut vm, &mut chunk);
-    } else if args.len() == 2 {
-        return run_file(&args[1], &mut vm, &mut chunk);
-    } else {
-        panic!("Usage: roxty [path]\n");
+    if options.engine == Engine::Tree {
+        return run_tree(options);
     }
 
-    ExitCode::SUCCESS
+    let mut vm = cli_vm(options.trace, &options.args);
+
+    match (options.eval, options.script) {
+        (Some(code), _) if options.disassemble => compile_exit_code(vm.disassemble_repl(&code)),
+        (Some(code), _) => exit_code(vm.interpret_repl(&code)),
+        (None, Some(path)) => run_file(&path, options.disassemble, &mut vm),
+        (None, None) => {
+            repl(|input| match input {
+                Input::Source(source) => {
+                    if let Err(error) = vm.interpret_repl(source) {
+                        eprintln!("{}", error);
+                    }
+                }
+                Input::Command(line) => {
+                    meta_command(line, &mut vm, || cli_vm(options.trace, &options.args))
+                }
+            });
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+#[cfg(feature = "tree-walk")]
+fn run_tree(options: Options) -> ExitCode {
+    if options.disassemble || options.trace {
+        eprintln!("Options '--disassemble' and '--trace' need the VM engine.");
+        return ExitCode::from(64);
+    }
+
+    let enviroments = Rc::new(RefCell::new(Enviroments::new()));
+    enviroments.borrow_mut().set_args(options.args);
+    match (options.eval, options.script) {
+        (Some(code), _) => tree_exit_code(tree_walk::run(&code, &enviroments)),
+        (None, Some(path)) => match read_source(&path) {
+            Ok(source) => tree_exit_code(tree_walk::run(&source, &enviroments)),
+            Err(code) => code,
+        },
+        (None, None) => {
+            repl(|input| match input {
+                Input::Source(source) => {
+                    if let Err(error) = tree_walk::run(source, &enviroments) {
+                        eprintln!("{}", error);
+                    }
+                }
+                Input::Command(_) => {
+                    eprintln!("Meta-commands are only available with the VM engine.")
+                }
+            });
+            ExitCode::SUCCESS
+        }
+    }
 }
 
-fn repl<'a>(vm: &mut VM<'a>, chunk: &'a mut Chunk) {
+#[cfg(not(feature = "tree-walk"))]
+fn run_tree(_options: Options) -> ExitCode {
+    eprintln!("The tree-walk engine is not available in this build.");
+    ExitCode::from(64)
+}
+
+/// A complete piece of REPL input.
+enum Input<'a> {
+    Source(&'a str),
+    /// A colon-prefixed meta-command line.
+    Command(&'a str),
+}
+
+fn repl(mut handle: impl FnMut(Input)) {
+    let mut source = String::new();
     loop {
-        let mut line = String::new();
-        print!("> ");
+        print!("{}", if source.is_empty() { "> " } else { "... " });
         std::io::stdout().flush().unwrap();
+
+        let mut line = String::new();
         let check = std::io::stdin()
             .read_line(&mut line)
             .expect("Failed to read line");
         if check == 0 {
+            println!();
             break;
         }
 
-        // vm.interpret(line, chunk);
+        if source.is_empty() && line.trim_start().starts_with(':') {
+            handle(Input::Command(line.trim()));
+            continue;
+        }
+
+        source.push_str(&line);
+        if !is_complete(&source) {
+            continue;
+        }
+
+        handle(Input::Source(&source));
+        source.clear();
+    }
+}
+
+/// A VM set up with the tracer and script arguments given on the command
+/// line.
+fn cli_vm(trace: bool, args: &[String]) -> VM {
+    let mut vm = VM::new();
+    vm.init_vm();
+    if trace {
+        vm.set_tracer(Some(Box::new(StdoutTracer)));
+    }
+    vm.set_args(args.to_vec());
+    vm
+}
+
+/// Runs a colon-prefixed REPL command for inspecting the VM. `fresh_vm`
+/// builds the replacement for `:reset`.
+fn meta_command(line: &str, vm: &mut VM, fresh_vm: impl FnOnce() -> VM) {
+    let (command, argument) = match line.split_once(char::is_whitespace) {
+        Some((command, argument)) => (command, argument.trim()),
+        None => (line, ""),
+    };
+
+    match command {
+        ":globals" => {
+            for (name, value) in vm.globals() {
+                println!("{} = {}", name, value);
+            }
+        }
+        ":dis" => {
+            if let Err(error) = vm.disassemble_repl(argument) {
+                eprintln!("{}", error);
+            }
+        }
+        ":stack" => {
+            for value in vm.stack() {
+                print!("[ {} ]", value);
+            }
+            println!();
+        }
+        ":load" => match std::fs::read_to_string(argument) {
+            Ok(source) => {
+                if let Err(error) = vm.interpret(&source) {
+                    eprintln!("{}", error);
+                }
+            }
+            Err(error) => eprintln!("Could not read '{}': {}", argument, error),
+        },
+        ":reset" => *vm = fresh_vm(),
+        ":gc" => {
+            let before = vm.heap_stats();
+            vm.collect_garbage();
+            let after = vm.heap_stats();
+            println!(
+                "collected {} bytes ({} objects), {} bytes in {} objects live, next at {}",
+                before.bytes_allocated - after.bytes_allocated,
+                before.objects - after.objects,
+                after.bytes_allocated,
+                after.objects,
+                after.next_gc
+            );
+        }
+        _ => eprintln!("Unknown command '{}'.", command),
+    }
+}
+
+/// Whether `source` has no unclosed braces, parentheses or strings, so the
+/// REPL can stop reading continuation lines.
+fn is_complete(source: &str) -> bool {
+    let mut depth = 0;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '(' => depth += 1,
+            '}' | ')' => depth -= 1,
+            '"' if !chars.by_ref().any(|c| c == '"') => return false,
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            _ => {}
+        }
+    }
+    // Surplus closers are left for the compiler to report.
+    depth <= 0
+}
+
+fn read_source(path: &str) -> Result<String, ExitCode> {
+    std::fs::read_to_string(path).map_err(|error| {
+        eprintln!("Could not read file '{}': {}", path, error);
+        ExitCode::from(74)
+    })
+}
+
+fn run_file(path: &str, disassemble: bool, vm: &mut VM) -> ExitCode {
+    let source = match read_source(path) {
+        Ok(source) => source,
+        Err(code) => return code,
+    };
+
+    if disassemble {
+        compile_exit_code(vm.disassemble(&source))
+    } else {
+        exit_code(vm.interpret(&source))
     }
 }
 
-fn run_file<'a>(path: &str, vm: &mut VM<'a>, chunk: &'a mut Chunk) -> ExitCode {
-    // let mut chunk = Chunk::init_chunk();
+fn exit_code(result: Result<(), InterpretError>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            match error {
+                InterpretError::Compile(_) => ExitCode::from(65),
+                InterpretError::Runtime(_) => ExitCode::from(70),
+            }
+        }
+    }
+}
 
-    match std::fs::read_to_string(path) {
-        Ok(source) => {
-            let result = vm.interpret(source, chunk);
+fn compile_exit_code(result: Result<(), CompileError>) -> ExitCode {
+    exit_code(result.map_err(InterpretError::Compile))
+}
 
-            match result {
-                Ok(_) => ExitCode::SUCCESS,
-                Err(error) => match error {
-                    InterpretResultError::INTERPRET_COMPILE_ERROR => ExitCode::from(65),
-                    InterpretResultError::INTERPRET_RUNTIME_ERROR => ExitCode::from(70),
-                    InterpretResultError::INTERPRET_OK => ExitCode::SUCCESS,
-                },
+#[cfg(feature = "tree-walk")]
+fn tree_exit_code(result: Result<(), TreeWalkError>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            match error {
+                TreeWalkError::Syntax(_) => ExitCode::from(65),
+                TreeWalkError::Runtime { .. } => ExitCode::from(70),
             }
         }
-        Err(_) => ExitCode::from(74),
     }
 }
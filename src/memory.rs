@@ -1 +1,245 @@
+use std::{
+    borrow::Borrow,
+    collections::HashSet,
+    hash::{Hash, Hasher},
+};
 
+use crate::{
+    object::{Gc, Obj, ObjString},
+    value::Value,
+};
+
+const GC_HEAP_GROW_FACTOR: usize = 2;
+const GC_INITIAL_THRESHOLD: usize = 1024 * 1024;
+
+/// Snapshot of the heap's bookkeeping, for diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapStats {
+    pub objects: usize,
+    pub bytes_allocated: usize,
+    /// Allocation volume at which the next collection is triggered.
+    pub next_gc: usize,
+}
+
+/// Owns every object the VM allocates. Objects are reclaimed by a
+/// mark-and-sweep collector: the VM marks its roots through `mark_value` and
+/// `mark_object`, then `collect_garbage` traces and frees the rest.
+pub struct Heap {
+    objects: Vec<Obj>,
+    strings: HashSet<InternedString>,
+    gray_stack: Vec<Obj>,
+    bytes_allocated: usize,
+    next_gc: usize,
+}
+
+/// Entry of the intern table. It hashes and compares by the string contents
+/// so the table can be probed with a plain `&str` before allocating.
+struct InternedString(Gc<ObjString>);
+
+impl Hash for InternedString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.chars.hash(state);
+    }
+}
+
+impl PartialEq for InternedString {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.chars == other.0.chars
+    }
+}
+
+impl Eq for InternedString {}
+
+impl Borrow<str> for InternedString {
+    fn borrow(&self) -> &str {
+        &self.0.chars
+    }
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self {
+            objects: Vec::new(),
+            strings: HashSet::new(),
+            gray_stack: Vec::new(),
+            bytes_allocated: 0,
+            next_gc: GC_INITIAL_THRESHOLD,
+        }
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        HeapStats {
+            objects: self.objects.len(),
+            bytes_allocated: self.bytes_allocated,
+            next_gc: self.next_gc,
+        }
+    }
+
+    /// Whether the next allocation should be preceded by a collection.
+    pub fn should_collect(&self) -> bool {
+        cfg!(feature = "stress-gc") || self.bytes_allocated > self.next_gc
+    }
+
+    pub fn alloc<T>(&mut self, value: T) -> Gc<T>
+    where
+        Obj: From<Gc<T>>,
+    {
+        let object = Gc::new(value);
+        let obj = Obj::from(object);
+        self.bytes_allocated += obj.size();
+        self.objects.push(obj);
+
+        #[cfg(feature = "log-gc")]
+        eprintln!(
+            "{:?} allocate {} for {}",
+            object,
+            obj.size(),
+            obj.type_name()
+        );
+
+        object
+    }
+
+    /// Looks up an already interned string without allocating.
+    pub fn find_string(&self, chars: &str) -> Option<Gc<ObjString>> {
+        self.strings.get(chars).map(|interned| interned.0)
+    }
+
+    pub fn copy_string(&mut self, chars: &str) -> Gc<ObjString> {
+        match self.strings.get(chars) {
+            Some(interned) => interned.0,
+            None => self.allocate_string(chars.to_string()),
+        }
+    }
+
+    pub fn take_string(&mut self, chars: String) -> Gc<ObjString> {
+        match self.strings.get(chars.as_str()) {
+            Some(interned) => interned.0,
+            None => self.allocate_string(chars),
+        }
+    }
+
+    fn allocate_string(&mut self, chars: String) -> Gc<ObjString> {
+        let string = self.alloc(ObjString { chars });
+        self.strings.insert(InternedString(string));
+        string
+    }
+
+    pub fn mark_value(&mut self, value: Value) {
+        if let Value::Obj(obj) = value {
+            self.mark_object(obj);
+        }
+    }
+
+    pub fn mark_object(&mut self, obj: Obj) {
+        if obj.is_marked() {
+            return;
+        }
+
+        #[cfg(feature = "log-gc")]
+        eprintln!("mark {}", obj);
+
+        obj.set_marked(true);
+        self.gray_stack.push(obj);
+    }
+
+    /// Traces everything reachable from the marked roots and frees the rest.
+    pub fn collect_garbage(&mut self) {
+        #[cfg(feature = "log-gc")]
+        let before = self.bytes_allocated;
+
+        self.trace_references();
+        self.strings.retain(|string| string.0.is_marked());
+        self.sweep();
+
+        self.next_gc = self.bytes_allocated * GC_HEAP_GROW_FACTOR;
+
+        #[cfg(feature = "log-gc")]
+        eprintln!(
+            "   collected {} bytes (from {} to {}) next at {}",
+            before - self.bytes_allocated,
+            before,
+            self.bytes_allocated,
+            self.next_gc
+        );
+    }
+
+    fn trace_references(&mut self) {
+        while let Some(obj) = self.gray_stack.pop() {
+            self.blacken_object(obj);
+        }
+    }
+
+    fn blacken_object(&mut self, obj: Obj) {
+        #[cfg(feature = "log-gc")]
+        eprintln!("blacken {}", obj);
+
+        match obj {
+            Obj::String(_) | Obj::Native(_) => {}
+            Obj::Function(function) => {
+                if let Some(name) = function.name {
+                    self.mark_object(Obj::String(name));
+                }
+                for &constant in &function.chunk.constants.values {
+                    self.mark_value(constant);
+                }
+            }
+            Obj::Closure(closure) => {
+                self.mark_object(Obj::Function(closure.function));
+                for &upvalue in &closure.upvalues {
+                    self.mark_object(Obj::Upvalue(upvalue));
+                }
+            }
+            Obj::Upvalue(upvalue) => {
+                if let Some(closed) = upvalue.closed {
+                    self.mark_value(closed);
+                }
+            }
+            Obj::Class(klass) => {
+                self.mark_object(Obj::String(klass.name));
+                for (&name, &method) in &klass.methods {
+                    self.mark_object(Obj::String(name));
+                    self.mark_object(Obj::Closure(method));
+                }
+            }
+            Obj::Instance(instance) => {
+                self.mark_object(Obj::Class(instance.klass));
+                for (&name, &value) in &instance.fields {
+                    self.mark_object(Obj::String(name));
+                    self.mark_value(value);
+                }
+            }
+            Obj::BoundMethod(bound) => {
+                self.mark_value(bound.receiver);
+                self.mark_object(Obj::Closure(bound.method));
+            }
+        }
+    }
+
+    fn sweep(&mut self) {
+        let mut bytes_freed = 0;
+        self.objects.retain(|obj| {
+            if obj.is_marked() {
+                obj.set_marked(false);
+                return true;
+            }
+
+            #[cfg(feature = "log-gc")]
+            eprintln!("free {}", obj.type_name());
+
+            bytes_freed += obj.size();
+            unsafe { obj.free() };
+            false
+        });
+        self.bytes_allocated -= bytes_freed;
+    }
+}
+
+impl Drop for Heap {
+    fn drop(&mut self) {
+        self.strings.clear();
+        for object in self.objects.drain(..) {
+            unsafe { object.free() }
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+use crate::{chunk::Chunk, error::RuntimeError, host, value::Value, vm::VM};
+
+/// A handle to an object living on the VM heap. Handles are plain pointers:
+/// copying one does not copy the object, and the `Heap` that allocated it is
+/// responsible for freeing it once the collector finds it unreachable.
+pub struct Gc<T> {
+    ptr: NonNull<GcBox<T>>,
+}
+
+/// Header the collector keeps in front of every object.
+struct GcBox<T> {
+    is_marked: Cell<bool>,
+    value: T,
+}
+
+impl<T> Gc<T> {
+    pub(crate) fn new(value: T) -> Self {
+        let boxed = Box::new(GcBox {
+            is_marked: Cell::new(false),
+            value,
+        });
+        Self {
+            ptr: NonNull::from(Box::leak(boxed)),
+        }
+    }
+
+    pub(crate) fn is_marked(&self) -> bool {
+        unsafe { self.ptr.as_ref() }.is_marked.get()
+    }
+
+    pub(crate) fn set_marked(&self, is_marked: bool) {
+        unsafe { self.ptr.as_ref() }.is_marked.set(is_marked);
+    }
+
+    /// # Safety
+    /// The handle, and every copy of it, must not be used after this call.
+    pub(crate) unsafe fn free(self) {
+        drop(Box::from_raw(self.ptr.as_ptr()));
+    }
+}
+
+impl<T> Clone for Gc<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Gc<T> {}
+
+impl<T> Deref for Gc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &self.ptr.as_ref().value }
+    }
+}
+
+impl<T> DerefMut for Gc<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut self.ptr.as_mut().value }
+    }
+}
+
+impl<T> PartialEq for Gc<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
+
+impl<T> Eq for Gc<T> {}
+
+impl<T> Hash for Gc<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ptr.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Gc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Gc({:p})", self.ptr)
+    }
+}
+
+#[derive(Debug)]
+pub struct ObjString {
+    pub chars: String,
+}
+
+#[derive(Debug)]
+pub struct ObjFunction {
+    pub arity: usize,
+    pub upvalue_count: usize,
+    pub chunk: Chunk,
+    /// `None` for the implicit top-level script function.
+    pub name: Option<Gc<ObjString>>,
+}
+
+impl ObjFunction {
+    pub fn new(name: Option<Gc<ObjString>>) -> Self {
+        Self {
+            arity: 0,
+            upvalue_count: 0,
+            chunk: Chunk::init_chunk(),
+            name,
+        }
+    }
+}
+
+impl fmt::Display for ObjFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "<fn {}>", name.chars),
+            None => write!(f, "<script>"),
+        }
+    }
+}
+
+/// Signature of a function implemented in Rust and callable from scripts.
+/// Returning a handle from another VM makes the call panic.
+pub type NativeFn = fn(&mut VM, &[host::Value]) -> Result<host::Value, RuntimeError>;
+
+pub struct ObjNative {
+    pub arity: usize,
+    pub function: NativeFn,
+}
+
+impl ObjNative {
+    pub fn new(arity: usize, function: NativeFn) -> Self {
+        Self { arity, function }
+    }
+}
+
+impl fmt::Debug for ObjNative {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ObjNative")
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A function together with the variables it captured from enclosing
+/// scopes. Every function value the VM hands out at runtime is a closure.
+#[derive(Debug)]
+pub struct ObjClosure {
+    pub function: Gc<ObjFunction>,
+    pub upvalues: Vec<Gc<ObjUpvalue>>,
+}
+
+impl ObjClosure {
+    pub fn new(function: Gc<ObjFunction>) -> Self {
+        Self {
+            function,
+            upvalues: Vec::with_capacity(function.upvalue_count),
+        }
+    }
+}
+
+/// A captured variable. While the variable is still alive on the VM stack
+/// the upvalue is open and points at its slot; once the variable goes out of
+/// scope its value moves into `closed`.
+#[derive(Debug)]
+pub struct ObjUpvalue {
+    pub location: usize,
+    pub closed: Option<Value>,
+}
+
+impl ObjUpvalue {
+    pub fn new(location: usize) -> Self {
+        Self {
+            location,
+            closed: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ObjClass {
+    pub name: Gc<ObjString>,
+    pub methods: HashMap<Gc<ObjString>, Gc<ObjClosure>>,
+}
+
+impl ObjClass {
+    pub fn new(name: Gc<ObjString>) -> Self {
+        Self {
+            name,
+            methods: HashMap::new(),
+        }
+    }
+}
+
+/// An instance of a class. Fields are created on first assignment, so every
+/// instance carries its own table.
+#[derive(Debug)]
+pub struct ObjInstance {
+    pub klass: Gc<ObjClass>,
+    pub fields: HashMap<Gc<ObjString>, Value>,
+}
+
+impl ObjInstance {
+    pub fn new(klass: Gc<ObjClass>) -> Self {
+        Self {
+            klass,
+            fields: HashMap::new(),
+        }
+    }
+}
+
+/// A method looked up on an instance, remembering the instance so that
+/// `this` is bound when the method is eventually called.
+#[derive(Debug)]
+pub struct ObjBoundMethod {
+    pub receiver: Value,
+    pub method: Gc<ObjClosure>,
+}
+
+impl ObjBoundMethod {
+    pub fn new(receiver: Value, method: Gc<ObjClosure>) -> Self {
+        Self { receiver, method }
+    }
+}
+
+/// A reference to a heap-allocated object owned by the VM. Strings are
+/// interned, so two objects are equal only if they are the same object.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Obj {
+    String(Gc<ObjString>),
+    Function(Gc<ObjFunction>),
+    Closure(Gc<ObjClosure>),
+    Upvalue(Gc<ObjUpvalue>),
+    Class(Gc<ObjClass>),
+    Instance(Gc<ObjInstance>),
+    BoundMethod(Gc<ObjBoundMethod>),
+    Native(Gc<ObjNative>),
+}
+
+impl Obj {
+    pub(crate) fn is_marked(&self) -> bool {
+        match self {
+            Obj::String(string) => string.is_marked(),
+            Obj::Function(function) => function.is_marked(),
+            Obj::Closure(closure) => closure.is_marked(),
+            Obj::Upvalue(upvalue) => upvalue.is_marked(),
+            Obj::Class(klass) => klass.is_marked(),
+            Obj::Instance(instance) => instance.is_marked(),
+            Obj::BoundMethod(bound) => bound.is_marked(),
+            Obj::Native(native) => native.is_marked(),
+        }
+    }
+
+    pub(crate) fn set_marked(&self, is_marked: bool) {
+        match self {
+            Obj::String(string) => string.set_marked(is_marked),
+            Obj::Function(function) => function.set_marked(is_marked),
+            Obj::Closure(closure) => closure.set_marked(is_marked),
+            Obj::Upvalue(upvalue) => upvalue.set_marked(is_marked),
+            Obj::Class(klass) => klass.set_marked(is_marked),
+            Obj::Instance(instance) => instance.set_marked(is_marked),
+            Obj::BoundMethod(bound) => bound.set_marked(is_marked),
+            Obj::Native(native) => native.set_marked(is_marked),
+        }
+    }
+
+    /// Approximate number of bytes the object accounts for on the heap. It
+    /// only depends on data fixed at allocation time so the same amount is
+    /// credited back when the object is freed, and only on the object's own
+    /// data since objects it points to may already be freed by then.
+    pub(crate) fn size(&self) -> usize {
+        match self {
+            Obj::String(string) => mem::size_of::<GcBox<ObjString>>() + string.chars.len(),
+            Obj::Function(_) => mem::size_of::<GcBox<ObjFunction>>(),
+            Obj::Closure(closure) => {
+                mem::size_of::<GcBox<ObjClosure>>()
+                    + closure.upvalues.capacity() * mem::size_of::<Gc<ObjUpvalue>>()
+            }
+            Obj::Upvalue(_) => mem::size_of::<GcBox<ObjUpvalue>>(),
+            Obj::Class(_) => mem::size_of::<GcBox<ObjClass>>(),
+            Obj::Instance(_) => mem::size_of::<GcBox<ObjInstance>>(),
+            Obj::BoundMethod(_) => mem::size_of::<GcBox<ObjBoundMethod>>(),
+            Obj::Native(_) => mem::size_of::<GcBox<ObjNative>>(),
+        }
+    }
+
+    #[cfg(feature = "log-gc")]
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Obj::String(_) => "string",
+            Obj::Function(_) => "function",
+            Obj::Closure(_) => "closure",
+            Obj::Upvalue(_) => "upvalue",
+            Obj::Class(_) => "class",
+            Obj::Instance(_) => "instance",
+            Obj::BoundMethod(_) => "bound method",
+            Obj::Native(_) => "native",
+        }
+    }
+
+    /// # Safety
+    /// See [`Gc::free`].
+    pub(crate) unsafe fn free(self) {
+        match self {
+            Obj::String(string) => string.free(),
+            Obj::Function(function) => function.free(),
+            Obj::Closure(closure) => closure.free(),
+            Obj::Upvalue(upvalue) => upvalue.free(),
+            Obj::Class(klass) => klass.free(),
+            Obj::Instance(instance) => instance.free(),
+            Obj::BoundMethod(bound) => bound.free(),
+            Obj::Native(native) => native.free(),
+        }
+    }
+}
+
+impl From<Gc<ObjString>> for Obj {
+    fn from(string: Gc<ObjString>) -> Self {
+        Obj::String(string)
+    }
+}
+
+impl From<Gc<ObjFunction>> for Obj {
+    fn from(function: Gc<ObjFunction>) -> Self {
+        Obj::Function(function)
+    }
+}
+
+impl From<Gc<ObjClosure>> for Obj {
+    fn from(closure: Gc<ObjClosure>) -> Self {
+        Obj::Closure(closure)
+    }
+}
+
+impl From<Gc<ObjUpvalue>> for Obj {
+    fn from(upvalue: Gc<ObjUpvalue>) -> Self {
+        Obj::Upvalue(upvalue)
+    }
+}
+
+impl From<Gc<ObjClass>> for Obj {
+    fn from(klass: Gc<ObjClass>) -> Self {
+        Obj::Class(klass)
+    }
+}
+
+impl From<Gc<ObjInstance>> for Obj {
+    fn from(instance: Gc<ObjInstance>) -> Self {
+        Obj::Instance(instance)
+    }
+}
+
+impl From<Gc<ObjBoundMethod>> for Obj {
+    fn from(bound: Gc<ObjBoundMethod>) -> Self {
+        Obj::BoundMethod(bound)
+    }
+}
+
+impl From<Gc<ObjNative>> for Obj {
+    fn from(native: Gc<ObjNative>) -> Self {
+        Obj::Native(native)
+    }
+}
+
+impl fmt::Display for Obj {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Obj::String(string) => write!(f, "{}", string.chars),
+            Obj::Function(function) => write!(f, "{}", **function),
+            Obj::Closure(closure) => write!(f, "{}", *closure.function),
+            Obj::Upvalue(_) => write!(f, "upvalue"),
+            Obj::Class(klass) => write!(f, "{}", klass.name.chars),
+            Obj::Instance(instance) => write!(f, "{} instance", instance.klass.name.chars),
+            Obj::BoundMethod(bound) => write!(f, "{}", *bound.method.function),
+            Obj::Native(_) => write!(f, "<native fn>"),
+        }
+    }
+}
@@ -3,6 +3,11 @@ pub struct Scanner {
     pub start: usize,
     pub current: usize,
     pub line: usize,
+    /// Index in `source` where the current line begins.
+    pub line_start: usize,
+    /// Index and column of the last token start on the current line, so
+    /// columns are counted in characters without rescanning the line.
+    column_mark: (usize, usize),
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -61,6 +66,7 @@ pub struct Token {
     pub start: usize,
     pub length: usize,
     pub line: usize,
+    pub column: usize,
     pub error: Option<&'static str>,
 }
 impl Scanner {
@@ -70,6 +76,8 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            column_mark: (0, 1),
         }
     }
 
@@ -90,14 +98,11 @@ impl Scanner {
                 '\n' => {
                     self.line += 1;
                     self.advance();
+                    self.line_start = self.current;
                 }
-                '/' => {
-                    if self.peek_next() == '/' {
-                        while self.peek() != '\n' && !self.is_at_end() {
-                            self.advance();
-                        }
-                    } else {
-                        return;
+                '/' if self.peek_next() == '/' => {
+                    while self.peek() != '\n' && !self.is_at_end() {
+                        self.advance();
                     }
                 }
                 _ => {
@@ -107,24 +112,30 @@ impl Scanner {
         }
     }
 
+    // The scanner walks `source` a byte at a time so that `start` and
+    // `current` are always valid byte offsets to slice it with. Every byte of
+    // a multi-byte character is at least 0x80, so none of them can be mistaken
+    // for the ASCII characters the grammar is made of.
+
     pub fn peek(&self) -> char {
-        if self.is_at_end() {
-            return '\0';
-        }
-        self.source.chars().nth(self.current).unwrap()
+        self.byte_at(self.current)
     }
 
     pub fn peek_next(&self) -> char {
-        if self.is_at_end() {
-            '\0'
-        } else {
-            self.source.chars().nth(self.current + 1).unwrap()
+        self.byte_at(self.current + 1)
+    }
+
+    fn byte_at(&self, index: usize) -> char {
+        match self.source.as_bytes().get(index) {
+            Some(&byte) => byte as char,
+            None => '\0',
         }
     }
 
     pub fn scan_token(&mut self) -> Token {
         self.skip_whitespace();
         self.start = self.current;
+        self.mark_column();
 
         if self.is_at_end() {
             return self.make_token(TokenType::TOKEN_EOF);
@@ -132,7 +143,7 @@ impl Scanner {
 
         let c = self.advance();
 
-        if c.is_ascii_alphabetic() {
+        if c.is_ascii_alphabetic() || c == '_' {
             return self.identifier();
         }
         if c.is_ascii_digit() {
@@ -169,32 +180,38 @@ impl Scanner {
                 self.make_token(TokenType::TOKEN_LESS)
             }
             '>' => {
-                if self.matchi('>') {
+                if self.matchi('=') {
                     return self.make_token(TokenType::TOKEN_GREATER_EQUAL);
                 }
                 self.make_token(TokenType::TOKEN_GREATER)
             }
             '"' => self.string(),
 
-            _ => self.error_token("Unexpected character."),
+            _ => {
+                // Take the rest of a multi-byte character along with it.
+                while !self.is_at_end() && self.source.as_bytes()[self.current] & 0xC0 == 0x80 {
+                    self.current += 1;
+                }
+                self.error_token("Unexpected character.")
+            }
         }
     }
 
     fn identifier(&mut self) -> Token {
-        while self.peek().is_ascii_alphanumeric() {
+        while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
             self.advance();
         }
         self.make_token(self.identifier_type())
     }
 
     fn identifier_type(&self) -> TokenType {
-        match self.source.chars().nth(self.start).unwrap() {
+        match self.byte_at(self.start) {
             'a' => self.check_keyword(1, 2, "nd", TokenType::TOKEN_AND),
             'c' => self.check_keyword(1, 4, "lass", TokenType::TOKEN_CLASS),
             'e' => self.check_keyword(1, 3, "lse", TokenType::TOKEN_ELSE),
             'f' => {
                 if self.current - self.start > 1 {
-                    match self.source.chars().nth(self.start + 1).unwrap() {
+                    match self.byte_at(self.start + 1) {
                         'a' => self.check_keyword(2, 3, "lse", TokenType::TOKEN_FALSE),
                         'o' => self.check_keyword(2, 1, "r", TokenType::TOKEN_FOR),
                         'u' => self.check_keyword(2, 1, "n", TokenType::TOKEN_FUN),
@@ -212,7 +229,7 @@ impl Scanner {
             's' => self.check_keyword(1, 4, "uper", TokenType::TOKEN_SUPER),
             't' => {
                 if self.current - self.start > 1 {
-                    match self.source.chars().nth(self.start + 1).unwrap() {
+                    match self.byte_at(self.start + 1) {
                         'h' => self.check_keyword(2, 2, "is", TokenType::TOKEN_THIS),
                         'r' => self.check_keyword(2, 2, "ue", TokenType::TOKEN_TRUE),
                         _ => TokenType::TOKEN_IDENTIFIER,
@@ -229,7 +246,7 @@ impl Scanner {
 
     fn check_keyword(&self, start: usize, length: usize, rest: &str, typo: TokenType) -> TokenType {
         if self.current - self.start == start + length
-            && rest == &self.source[self.start..self.current + 1]
+            && rest == &self.source[self.start + start..self.current]
         {
             return typo;
         }
@@ -250,10 +267,13 @@ impl Scanner {
         self.make_token(TokenType::TOKEN_NUMBER)
     }
     fn string(&mut self) -> Token {
-        while self.peek() != '"' && self.is_at_end() {
+        while self.peek() != '"' && !self.is_at_end() {
             if self.peek() == '\n' {
                 self.line += 1;
                 self.advance();
+                self.line_start = self.current;
+            } else {
+                self.advance();
             }
         }
         if self.is_at_end() {
@@ -265,10 +285,8 @@ impl Scanner {
     }
 
     fn matchi(&mut self, letter: char) -> bool {
-        if self.is_at_end() {
+        if self.is_at_end() || self.peek() != letter {
             false
-        } else if self.source.chars().nth(self.current).unwrap() != letter {
-            return false;
         } else {
             self.current += 1;
             true
@@ -277,11 +295,32 @@ impl Scanner {
 
     fn advance(&mut self) -> char {
         self.current += 1;
-        self.source.chars().nth(self.current - 1).unwrap()
+        self.byte_at(self.current - 1)
     }
 
     fn is_at_end(&self) -> bool {
-        self.current + 1 == self.source.len()
+        self.current >= self.source.len()
+    }
+
+    /// Counts the characters between the last token start and this one.
+    fn mark_column(&mut self) {
+        let (mut index, mut column) = self.column_mark;
+        if index < self.line_start {
+            (index, column) = (self.line_start, 1);
+        }
+        column += self.source[index..self.start].chars().count();
+        self.column_mark = (self.start, column);
+    }
+
+    /// One-based column, in characters, of the token being scanned. A string
+    /// spanning lines starts before the current line, so it is reported at
+    /// column one.
+    fn column(&self) -> usize {
+        if self.start < self.line_start {
+            1
+        } else {
+            self.column_mark.1
+        }
     }
 
     fn make_token(&self, typo: TokenType) -> Token {
@@ -290,6 +329,7 @@ impl Scanner {
             start: self.start,
             length: self.current - self.start,
             line: self.line,
+            column: self.column(),
             error: None,
         }
     }
@@ -297,9 +337,10 @@ impl Scanner {
     fn error_token(&self, message: &'static str) -> Token {
         Token {
             typo: TokenType::TOKEN_ERROR,
-            start: 0,
-            length: message.len(),
+            start: self.start,
+            length: self.current - self.start,
             line: self.line,
+            column: self.column(),
             error: Some(message),
         }
     }
@@ -0,0 +1,52 @@
+use crate::{
+    debug::disassemble_instruction,
+    host::Value,
+    object::{Gc, ObjFunction},
+};
+
+/// Hook the VM calls before executing each instruction. Install one with
+/// [`VM::set_tracer`](crate::VM::set_tracer); with none installed the VM
+/// skips tracing entirely.
+pub trait Tracer {
+    fn trace(&mut self, frame: &Frame, stack: &[Value]);
+}
+
+/// The call frame an instruction is about to execute in.
+pub struct Frame {
+    pub(crate) function: Gc<ObjFunction>,
+    pub(crate) ip: usize,
+}
+
+impl Frame {
+    /// `None` for the top-level script.
+    pub fn function_name(&self) -> Option<&str> {
+        self.function.name.as_ref().map(|name| name.chars.as_str())
+    }
+
+    /// Offset of the instruction in the function's bytecode.
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn line(&self) -> usize {
+        self.function.chunk.lines[self.ip]
+    }
+
+    /// Prints the instruction to stdout in the disassembler's format.
+    pub fn disassemble_instruction(&self) {
+        disassemble_instruction(&self.function.chunk, &self.ip);
+    }
+}
+
+/// Prints the value stack followed by each instruction, clox style.
+pub struct StdoutTracer;
+
+impl Tracer for StdoutTracer {
+    fn trace(&mut self, frame: &Frame, stack: &[Value]) {
+        for slot in stack {
+            print!("[ {} ]", slot);
+        }
+        println!();
+        frame.disassemble_instruction();
+    }
+}
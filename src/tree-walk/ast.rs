@@ -1,13 +1,13 @@
-use core::num;
 use std::{
+    cell::{Cell, RefCell},
     collections::HashMap,
-    env::args,
-    time::{SystemTime, UNIX_EPOCH},
+    fmt,
+    rc::Rc,
 };
 
-use crate::{
-    token::{self, Token, TokenType},
-    Enviroments,
+use super::{
+    token::{Token, TokenType},
+    Closure, Enviroments,
 };
 
 #[derive(Debug, Clone)]
@@ -17,13 +17,16 @@ pub enum Expr {
     Set(Box<Expr>, Token, Box<Expr>),
     Binary(Box<Expr>, Token, Box<Expr>),
     Grouping(Box<Expr>),
-    Variable(Token),
-    Assign(Token, Box<Expr>),
+    /// A variable read, with its scope distance once resolved. `None` means
+    /// a global.
+    Variable(Token, Option<usize>),
+    Assign(Token, Box<Expr>, Option<usize>),
     Logical(Box<Expr>, Token, Box<Expr>),
-    Call(Box<Expr>, Token, Vec<Option<Expr>>),
+    Call(Box<Expr>, Token, Vec<Expr>),
     Get(Box<Expr>, Token),
 }
 #[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum Stmt {
     ExprStmt(Expr),
     PrintStmt(Expr),
@@ -31,31 +34,96 @@ pub enum Stmt {
     Block(Vec<Stmt>),
     IfStmt(Expr, Box<Stmt>, Option<Box<Stmt>>),
     WhileStmt(Expr, Option<Box<Stmt>>),
-    Functions(Token, Vec<Token>, Box<Stmt>),
-    Return(Option<Expr>),
+    Functions(Rc<FunctionDecl>),
+    Return(Token, Option<Expr>),
     ClassDcl(Token, Vec<Stmt>),
 }
 
-#[derive(Debug, Clone)]
+/// A function declaration. It is shared between the syntax tree and every
+/// function value created from it, so declaring a function never copies
+/// its body.
+#[derive(Debug)]
+pub struct FunctionDecl {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+}
+
+/// Why evaluation stopped before reaching the end of a statement: either a
+/// `return` unwinding to the enclosing call, or a runtime error unwinding
+/// all the way out of the script.
+#[derive(Debug)]
+pub enum Unwind {
+    Return(LoxVal),
+    Error(usize, String),
+}
+
+/// How deep calls may nest before a stack overflow is reported. Like the
+/// VM's frame limit, the top-level script counts as one.
+const CALLS_MAX: usize = 64;
+
+thread_local! {
+    /// Number of function calls currently running.
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+fn runtime_error(token: &Token, message: impl Into<String>) -> Unwind {
+    Unwind::Error(token.line, message.into())
+}
+
+/// The Rust side of a native: takes the arguments and returns the result
+/// or an error message.
+type NativeFn = Box<dyn Fn(&[LoxVal]) -> Result<LoxVal, String>>;
+
+/// A function implemented in Rust, such as `clock`.
+pub struct LoxNative {
+    pub name: &'static str,
+    arity: usize,
+    function: NativeFn,
+}
+
+impl LoxNative {
+    pub fn new(
+        name: &'static str,
+        arity: usize,
+        function: impl Fn(&[LoxVal]) -> Result<LoxVal, String> + 'static,
+    ) -> Self {
+        Self {
+            name,
+            arity,
+            function: Box::new(function),
+        }
+    }
+}
+
+impl fmt::Debug for LoxNative {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LoxNative({})", self.name)
+    }
+}
+
+#[derive(Debug)]
 pub struct LoxKlass {
     name: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct LoxInstance {
-    klass: LoxKlass,
+    klass: Rc<LoxKlass>,
     fields: HashMap<String, LoxVal>,
 }
 
 impl LoxInstance {
-    fn get(&self, name: Token) -> LoxVal {
-        if self.fields.contains_key(&name.lexeme) {
-            return self.fields.get(&name.lexeme).unwrap().clone();
+    fn get(&self, name: &Token) -> Result<LoxVal, Unwind> {
+        match self.fields.get(&name.lexeme) {
+            Some(value) => Ok(value.clone()),
+            None => Err(runtime_error(
+                name,
+                format!("Undefined property '{}'.", name.lexeme),
+            )),
         }
-
-        panic!("ERRORE FIELD");
     }
-    fn set(&mut self, name: Token, value: LoxVal) {
+    fn set(&mut self, name: &Token, value: LoxVal) {
         self.fields.insert(name.lexeme.clone(), value);
     }
 }
@@ -65,358 +133,326 @@ pub enum LoxVal {
     String(String),
     Number(f64),
     Boolean(bool),
-    Functions(Box<Stmt>, Option<Enviroments>),
+    /// A declaration together with the scope it was declared in.
+    Functions(Rc<FunctionDecl>, Closure),
+    Native(Rc<LoxNative>),
     Nil,
-    Class(LoxKlass),
-    Instance(LoxInstance),
+    Class(Rc<LoxKlass>),
+    Instance(Rc<RefCell<LoxInstance>>),
 }
 
-impl LoxVal {
-    fn call(&mut self, enviroments: &mut Enviroments, arguments: Vec<LoxVal>) -> LoxVal {
-        if let LoxVal::Functions(fun, fun_env) = self {
-            if let Stmt::Functions(name, params, body) = fun.as_ref() {
-                let mut new_env = Enviroments {
-                    enclosing: Some(Box::new(fun_env.as_ref().unwrap().clone())),
-                    map: HashMap::new(),
-                };
+impl fmt::Display for LoxVal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoxVal::String(word) => write!(f, "{}", word),
+            LoxVal::Number(num) => write!(f, "{}", num),
+            LoxVal::Boolean(bol) => write!(f, "{}", bol),
+            LoxVal::Functions(fun, _) => write!(f, "<fn {}>", fun.name.lexeme),
+            LoxVal::Native(_) => write!(f, "<native fn>"),
+            LoxVal::Nil => write!(f, "nil"),
+            LoxVal::Class(klass) => write!(f, "{}", klass.name),
+            LoxVal::Instance(ins) => write!(f, "{} instance", ins.borrow().klass.name),
+        }
+    }
+}
 
-                for (i, param) in params.iter().enumerate() {
-                    new_env.define(param.lexeme.clone(), arguments[i].clone());
+impl LoxVal {
+    fn call(&self, paren: &Token, arguments: Vec<LoxVal>) -> Result<LoxVal, Unwind> {
+        match self {
+            LoxVal::Functions(fun, closure) => {
+                if arguments.len() != fun.params.len() {
+                    return Err(runtime_error(
+                        paren,
+                        format!(
+                            "Expected {} arguments but got {}.",
+                            fun.params.len(),
+                            arguments.len()
+                        ),
+                    ));
                 }
 
-                let val = body.eval(&mut new_env);
-                if let LoxVal::Nil = val {
-                } else {
-                    *fun_env = Some(new_env.clone());
+                let depth = CALL_DEPTH.get();
+                if depth + 1 >= CALLS_MAX {
+                    return Err(runtime_error(paren, "Stack overflow."));
+                }
 
-                    return val;
+                let new_env = Enviroments::new_enclosed(&closure.scope());
+                for (param, argument) in fun.params.iter().zip(arguments) {
+                    new_env.borrow_mut().define(param.lexeme.clone(), argument);
                 }
 
-                *fun_env = Some(new_env.clone());
-            }
-        }
+                CALL_DEPTH.set(depth + 1);
+                let result = execute_block(&fun.body, &new_env);
+                CALL_DEPTH.set(depth);
 
-        if let LoxVal::Class(kclass) = self {
-            let ins = LoxVal::Instance(LoxInstance {
-                klass: kclass.clone(),
-                fields: HashMap::new(),
-            });
-            enviroments.define(kclass.name.clone(), ins);
-            return enviroments.get(&kclass.name).clone();
+                match result {
+                    Ok(()) => Ok(LoxVal::Nil),
+                    Err(Unwind::Return(value)) => Ok(value),
+                    Err(error) => Err(error),
+                }
+            }
+            LoxVal::Native(native) => {
+                if arguments.len() != native.arity {
+                    return Err(runtime_error(
+                        paren,
+                        format!(
+                            "Expected {} arguments but got {}.",
+                            native.arity,
+                            arguments.len()
+                        ),
+                    ));
+                }
+                (native.function)(&arguments).map_err(|message| runtime_error(paren, message))
+            }
+            LoxVal::Class(kclass) => {
+                if !arguments.is_empty() {
+                    return Err(runtime_error(
+                        paren,
+                        format!("Expected 0 arguments but got {}.", arguments.len()),
+                    ));
+                }
+                Ok(LoxVal::Instance(Rc::new(RefCell::new(LoxInstance {
+                    klass: Rc::clone(kclass),
+                    fields: HashMap::new(),
+                }))))
+            }
+            _ => Err(runtime_error(paren, "Can only call functions and classes.")),
         }
-
-        LoxVal::Nil
     }
 }
-/*
-let n_env = Enviroments {
-                  enclosing: Some(Box::new(enviroments.clone())),
-                  map: HashMap::new(),
-              };
-
-              let param_values = params.
-
-              for arg in params {
-                  n_env.define(arg.lexeme.clone(), value)
-              }
 
-              *enviroments = n_env.enclosing.unwrap().as_ref().clone();
+/// Runs `block` directly in `enviroments`, stopping at the first statement
+/// that unwinds.
+fn execute_block(block: &[Stmt], enviroments: &Rc<RefCell<Enviroments>>) -> Result<(), Unwind> {
+    for blk in block {
+        blk.eval(enviroments)?;
+    }
+    Ok(())
+}
 
-              LoxVal::Nil */
 impl Stmt {
-    // pub fn call(&self, callee: &Expr, args: &Vec<Expr>, enviroments: &mut Enviroments) -> LoxVal {
-    //     todo!()
-    // }
-    pub fn eval(&self, enviroments: &mut Enviroments) -> LoxVal {
+    pub fn eval(&self, enviroments: &Rc<RefCell<Enviroments>>) -> Result<(), Unwind> {
         match self {
-            Stmt::ClassDcl(name, methods) => {
-                enviroments.define(
+            Stmt::ClassDcl(name, _methods) => {
+                enviroments.borrow_mut().define(
                     name.lexeme.clone(),
-                    LoxVal::Class(LoxKlass {
+                    LoxVal::Class(Rc::new(LoxKlass {
                         name: name.lexeme.clone(),
-                    }),
+                    })),
                 );
 
-                LoxVal::Nil
+                Ok(())
             }
-            Stmt::Return(exp) => match exp {
-                Some(expr) => expr.interpret(enviroments),
-                None => LoxVal::Nil,
-            },
-            Stmt::Functions(name, _params, _body) => {
-                let fun = LoxVal::Functions(Box::new(self.clone()), Some(enviroments.clone()));
-                enviroments.define(name.lexeme.clone(), fun);
-                LoxVal::Nil
+            Stmt::Return(_keyword, exp) => {
+                let value = match exp {
+                    Some(expr) => expr.interpret(enviroments)?,
+                    None => LoxVal::Nil,
+                };
+                Err(Unwind::Return(value))
+            }
+            Stmt::Functions(fun) => {
+                let value =
+                    LoxVal::Functions(Rc::clone(fun), Closure::Strong(Rc::clone(enviroments)));
+                enviroments
+                    .borrow_mut()
+                    .define(fun.name.lexeme.clone(), value);
+                Ok(())
             }
             Stmt::WhileStmt(condition, body) => {
-                while is_truthy(condition.interpret(enviroments)) {
-                    match body {
-                        Some(body) => {
-                            body.eval(enviroments);
-                        }
-                        None => {}
+                while is_truthy(&condition.interpret(enviroments)?) {
+                    if let Some(body) = body {
+                        body.eval(enviroments)?;
                     }
                 }
 
-                LoxVal::Nil
+                Ok(())
             }
             Stmt::IfStmt(cond, then, else_stmt) => {
-                if is_truthy(cond.interpret(enviroments)) {
-                    let val = then.eval(enviroments);
-                    if let LoxVal::Nil = val {
-                    } else {
-                        return val;
-                    }
-                }
-                match else_stmt {
-                    Some(else_b) => {
-                        else_b.eval(enviroments);
-                    }
-                    None => {}
+                if is_truthy(&cond.interpret(enviroments)?) {
+                    then.eval(enviroments)
+                } else if let Some(else_b) = else_stmt {
+                    else_b.eval(enviroments)
+                } else {
+                    Ok(())
                 }
-
-                LoxVal::Nil
             }
             Stmt::PrintStmt(expr) => {
-                let val = expr.interpret(enviroments);
-                match val {
-                    LoxVal::Boolean(bol) => println!("{bol}"),
-                    LoxVal::Number(num) => println!("{num}"),
-                    LoxVal::String(word) => println!("{word}"),
-                    LoxVal::Nil => println!("Nil"),
-                    LoxVal::Functions(_, _) => println!("fun"),
-                    LoxVal::Class(_) => println!("class"),
-                    LoxVal::Instance(ins) => println!("{}", ins.klass.name),
-                }
-                LoxVal::Nil
+                let val = expr.interpret(enviroments)?;
+                println!("{}", val);
+                Ok(())
             }
             Stmt::ExprStmt(expr) => {
-                expr.interpret(enviroments);
-                LoxVal::Nil
+                expr.interpret(enviroments)?;
+                Ok(())
             }
             Stmt::VarDecl(name, init) => {
                 let val = match init {
-                    Some(exp) => exp.interpret(enviroments),
+                    Some(exp) => exp.interpret(enviroments)?,
                     None => LoxVal::Nil,
                 };
 
-                println!("{:?} {:?}", name.lexeme, val);
-
-                enviroments.define(name.lexeme.clone(), val);
-
-                LoxVal::Nil
-            }
-            Stmt::Block(block) => {
-                let mut new_env = Enviroments {
-                    enclosing: Some(Box::new(enviroments.clone())),
-                    map: HashMap::new(),
-                };
-
-                for blk in block {
-                    let val = blk.eval(&mut new_env);
-
-                    if let LoxVal::Nil = val {
-                    } else {
-                        *enviroments = new_env.enclosing.unwrap().as_ref().clone();
+                enviroments.borrow_mut().define(name.lexeme.clone(), val);
 
-                        return val;
-                    }
-                }
-                *enviroments = new_env.enclosing.unwrap().as_ref().clone();
-
-                LoxVal::Nil
+                Ok(())
             }
+            Stmt::Block(block) => execute_block(block, &Enviroments::new_enclosed(enviroments)),
         }
     }
 }
 
 impl Expr {
-    pub fn interpret(&self, enviroments: &mut Enviroments) -> LoxVal {
+    pub fn interpret(&self, enviroments: &Rc<RefCell<Enviroments>>) -> Result<LoxVal, Unwind> {
         match self {
             Expr::Set(obj, name, value) => {
-                let mut nobj = obj.interpret(enviroments);
-
-                if let LoxVal::Instance(ins) = &mut nobj {
-                    let value = value.interpret(enviroments);
-                    ins.set(name.clone(), value.clone());
-                    enviroments.assign(ins.klass.name.clone(), value.clone());
-                    value
-                } else {
-                    panic!("Only Instances");
-                }
-            }
-            Expr::Get(expr, name) => {
-                let ans = expr.interpret(enviroments);
-                if let LoxVal::Instance(instance) = ans {
-                    return instance.get(name.clone());
-                }
-                panic!("ONLY CLASS CAN HAVE FIELDS");
+                let LoxVal::Instance(ins) = obj.interpret(enviroments)? else {
+                    return Err(runtime_error(name, "Only instances have fields."));
+                };
+                let value = value.interpret(enviroments)?;
+                ins.borrow_mut().set(name, value.clone());
+                Ok(value)
             }
-            Expr::Call(callee, _, arguments) => {
-                if let Expr::Variable(s) = callee.as_ref() {
-                    if s.lexeme == "clock" {
-                        return LoxVal::Number(
-                            SystemTime::elapsed(&UNIX_EPOCH).unwrap().as_millis() as f64 / 1000.0,
-                        );
-                    }
-                }
-                let mut callee = callee.interpret(enviroments);
-
-                let args = arguments
-                    .iter()
-                    .filter(|x| x.is_some())
-                    .map(|x| x.clone().unwrap().interpret(enviroments))
-                    .collect::<Vec<_>>();
-
-                let val = callee.call(enviroments, args);
+            Expr::Get(expr, name) => match expr.interpret(enviroments)? {
+                LoxVal::Instance(instance) => instance.borrow().get(name),
+                _ => Err(runtime_error(name, "Only instances have properties.")),
+            },
+            Expr::Call(callee_expr, paren, arguments) => {
+                let callee = callee_expr.interpret(enviroments)?;
 
-                if let LoxVal::Nil = val {
-                } else {
-                    return val;
+                let mut args = Vec::with_capacity(arguments.len());
+                for argument in arguments {
+                    args.push(argument.interpret(enviroments)?);
                 }
 
-                LoxVal::Nil
+                callee.call(paren, args)
             }
-            Expr::Literal(val) => val.clone(),
+            Expr::Literal(val) => Ok(val.clone()),
             Expr::Unary(pro, b_expr) => {
-                let b_exp = b_expr.interpret(enviroments);
+                let b_exp = b_expr.interpret(enviroments)?;
 
                 match pro.token_type {
                     TokenType::MINUS => match b_exp {
-                        LoxVal::Number(num) => LoxVal::Number(-num),
-                        LoxVal::String(to_num) => {
-                            let to_num = to_num.parse::<f64>();
-                            match to_num {
-                                Ok(val) => LoxVal::Number(-val),
-                                Err(_error) => panic!("ParseErrorFloat"),
-                            }
-                        }
-                        _ => {
-                            panic!("error");
-                        }
+                        LoxVal::Number(num) => Ok(LoxVal::Number(-num)),
+                        _ => Err(runtime_error(pro, "Operand must be a number.")),
                     },
-                    TokenType::BANG => LoxVal::Boolean(!(is_truthy(b_exp))),
-                    _ => {
-                        panic!("MORE")
-                    }
+                    TokenType::BANG => Ok(LoxVal::Boolean(!is_truthy(&b_exp))),
+                    _ => unreachable!("unary operator {:?}", pro.token_type),
                 }
             }
             Expr::Logical(left, opr, right) => {
-                let left = left.interpret(enviroments);
+                let left = left.interpret(enviroments)?;
 
                 if let TokenType::OR = opr.token_type {
-                    if is_truthy(left.clone()) {
-                        return left.clone();
+                    if is_truthy(&left) {
+                        return Ok(left);
                     }
-                } else if !is_truthy(left.clone()) {
-                    return left;
+                } else if !is_truthy(&left) {
+                    return Ok(left);
                 }
 
                 right.interpret(enviroments)
             }
 
-            Expr::Assign(name, expr) => {
-                let value = expr.interpret(enviroments);
-
-                enviroments.assign(name.lexeme.clone(), value.clone());
-                value
+            Expr::Assign(name, expr, depth) => {
+                let value = expr.interpret(enviroments)?;
+                assign_variable(enviroments, name, *depth, value.clone())?;
+                Ok(value)
             }
 
             Expr::Grouping(group) => group.interpret(enviroments),
 
             Expr::Binary(exp_left, tok, exp_right) => {
-                let left = exp_left.interpret(enviroments);
-                let right = exp_right.interpret(enviroments);
-
-                match tok.token_type {
-                    TokenType::EQUAL_EQUAL => LoxVal::Boolean(is_equally(left, right)),
-                    TokenType::BANG_EQUAL => LoxVal::Boolean(!(is_equally(left, right))),
-                    TokenType::LESS => match (left, right) {
-                        (LoxVal::Number(left_num), LoxVal::Number(right_num)) => {
-                            LoxVal::Boolean(left_num < right_num)
-                        }
-                        _ => panic!("LESS ERROR"),
-                    },
-                    TokenType::LESS_EQUAL => match (left, right) {
-                        (LoxVal::Number(left_num), LoxVal::Number(right_num)) => {
-                            LoxVal::Boolean(left_num <= right_num)
-                        }
-                        _ => panic!("LESS EQUAL ERROR"),
-                    },
-                    TokenType::GREATER => match (left, right) {
-                        (LoxVal::Number(left_num), LoxVal::Number(right_num)) => {
-                            LoxVal::Boolean(left_num > right_num)
-                        }
-                        _ => panic!("Greater ERROR"),
-                    },
-                    TokenType::GREATER_EQUAL => match (left, right) {
-                        (LoxVal::Number(left_num), LoxVal::Number(right_num)) => {
-                            LoxVal::Boolean(left_num >= right_num)
-                        }
-                        _ => panic!("Greater EQUAL ERROR"),
-                    },
-                    TokenType::PLUS => {
-                        if let LoxVal::Number(left_f) = left {
-                            if let LoxVal::Number(right_f) = right {
-                                return LoxVal::Number(left_f + right_f);
-                            }
-                        }
-
-                        if let LoxVal::String(left_s) = left {
-                            if let LoxVal::String(right_s) = right {
-                                return LoxVal::String(left_s + right_s.as_str());
-                            }
-                        }
-                        panic!("ANOTHEr type");
+                let left = exp_left.interpret(enviroments)?;
+                let right = exp_right.interpret(enviroments)?;
+
+                match (tok.token_type, left, right) {
+                    (TokenType::EQUAL_EQUAL, left, right) => {
+                        Ok(LoxVal::Boolean(is_equally(&left, &right)))
                     }
-                    TokenType::MINUS => {
-                        if let LoxVal::Number(left_f) = left {
-                            if let LoxVal::Number(right_f) = right {
-                                return LoxVal::Number(left_f - right_f);
-                            }
-                        }
-                        panic!("ANOTHEr type");
+                    (TokenType::BANG_EQUAL, left, right) => {
+                        Ok(LoxVal::Boolean(!is_equally(&left, &right)))
                     }
-                    TokenType::STAR => {
-                        if let LoxVal::Number(left_f) = left {
-                            if let LoxVal::Number(right_f) = right {
-                                return LoxVal::Number(left_f * right_f);
-                            }
-                        }
-                        panic!("ANOTHEr type");
+                    (TokenType::PLUS, LoxVal::Number(left_f), LoxVal::Number(right_f)) => {
+                        Ok(LoxVal::Number(left_f + right_f))
                     }
-                    TokenType::SLASH => {
-                        if let LoxVal::Number(left_f) = left {
-                            if let LoxVal::Number(right_f) = right {
-                                return LoxVal::Number(left_f / right_f);
-                            }
-                        }
-                        panic!("ANOTHEr type");
+                    (TokenType::PLUS, LoxVal::String(left_s), LoxVal::String(right_s)) => {
+                        Ok(LoxVal::String(left_s + right_s.as_str()))
                     }
-                    _ => {
-                        panic!("NO OPERATOR")
+                    (TokenType::PLUS, _, _) => Err(runtime_error(
+                        tok,
+                        "Operands must be two numbers or two strings.",
+                    )),
+                    (operator, LoxVal::Number(left_f), LoxVal::Number(right_f)) => {
+                        Ok(match operator {
+                            TokenType::LESS => LoxVal::Boolean(left_f < right_f),
+                            TokenType::LESS_EQUAL => LoxVal::Boolean(left_f <= right_f),
+                            TokenType::GREATER => LoxVal::Boolean(left_f > right_f),
+                            TokenType::GREATER_EQUAL => LoxVal::Boolean(left_f >= right_f),
+                            TokenType::MINUS => LoxVal::Number(left_f - right_f),
+                            TokenType::STAR => LoxVal::Number(left_f * right_f),
+                            TokenType::SLASH => LoxVal::Number(left_f / right_f),
+                            _ => unreachable!("binary operator {:?}", operator),
+                        })
                     }
+                    _ => Err(runtime_error(tok, "Operands must be numbers.")),
                 }
             }
-            Expr::Variable(var) => enviroments.get(&var.lexeme).clone(),
+            Expr::Variable(var, depth) => {
+                let value = match depth {
+                    Some(distance) => Enviroments::get_at(enviroments, *distance, &var.lexeme),
+                    None => Enviroments::globals(enviroments).borrow().get(&var.lexeme),
+                };
+                value.ok_or_else(|| {
+                    runtime_error(var, format!("Undefined variable '{}'.", var.lexeme))
+                })
+            }
         }
     }
 }
 
-fn is_truthy(val: LoxVal) -> bool {
+/// Assigns to the variable `name` resolved to `depth` scopes out, or to the
+/// global of that name when it was not resolved.
+fn assign_variable(
+    enviroments: &Rc<RefCell<Enviroments>>,
+    name: &Token,
+    depth: Option<usize>,
+    value: LoxVal,
+) -> Result<(), Unwind> {
+    let assigned = match depth {
+        Some(distance) => Enviroments::assign_at(enviroments, distance, &name.lexeme, value),
+        None => Enviroments::globals(enviroments)
+            .borrow_mut()
+            .assign(&name.lexeme, value),
+    };
+    if assigned {
+        Ok(())
+    } else {
+        Err(runtime_error(
+            name,
+            format!("Undefined variable '{}'.", name.lexeme),
+        ))
+    }
+}
+
+fn is_truthy(val: &LoxVal) -> bool {
     match val {
         LoxVal::Nil => false,
-        LoxVal::Boolean(booly) => booly,
+        LoxVal::Boolean(booly) => *booly,
         _ => true,
     }
 }
 
-fn is_equally(left: LoxVal, right: LoxVal) -> bool {
+fn is_equally(left: &LoxVal, right: &LoxVal) -> bool {
     match (left, right) {
         (LoxVal::Nil, LoxVal::Nil) => true,
-        (LoxVal::Nil, _) => false,
+        (LoxVal::Boolean(left_b), LoxVal::Boolean(right_b)) => left_b == right_b,
         (LoxVal::Number(left_n), LoxVal::Number(right_n)) => left_n == right_n,
         (LoxVal::String(left_n), LoxVal::String(right_n)) => left_n == right_n,
+        (LoxVal::Functions(left_f, left_env), LoxVal::Functions(right_f, right_env)) => {
+            Rc::ptr_eq(left_f, right_f) && left_env.same_scope(right_env)
+        }
+        (LoxVal::Native(left_n), LoxVal::Native(right_n)) => Rc::ptr_eq(left_n, right_n),
+        (LoxVal::Class(left_k), LoxVal::Class(right_k)) => Rc::ptr_eq(left_k, right_k),
+        (LoxVal::Instance(left_i), LoxVal::Instance(right_i)) => Rc::ptr_eq(left_i, right_i),
         (_, _) => false,
     }
 }
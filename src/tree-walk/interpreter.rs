@@ -1,20 +1,59 @@
-use crate::ast::{Expr, LoxVal};
+use std::{cell::RefCell, error::Error, fmt, rc::Rc};
 
-pub fn visit_literal_expr(expr: Expr) -> LoxVal {
-    match expr {
-        Expr::Literal(expr_val) => expr_val,
-        _ => {
-            panic!("asd");
+use super::{ast::Unwind, parser::parse, resolver::resolve, scanner::Scanner, Enviroments};
+
+/// An error reported by the tree-walk engine.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeWalkError {
+    /// Every problem found while scanning and parsing, already formatted.
+    Syntax(Vec<String>),
+    Runtime {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for TreeWalkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TreeWalkError::Syntax(errors) => write!(f, "{}", errors.join("\n")),
+            TreeWalkError::Runtime { line, message } => {
+                write!(f, "{}\n[line {}]", message, line)
+            }
         }
     }
 }
 
-fn visit_grouping_expr(expr: Expr) {
-    evaluate(expr);
+impl Error for TreeWalkError {}
+
+/// Scans, parses and runs `source`. Globals are defined in `enviroments`,
+/// so passing the same one again continues where the last run left off.
+pub fn run(source: &str, enviroments: &Rc<RefCell<Enviroments>>) -> Result<(), TreeWalkError> {
+    let mut scanner = Scanner::new(source);
+
+    let tokens = scanner
+        .scan_tokens()
+        .map_err(|(line, message)| TreeWalkError::Syntax(vec![report(line, "", &message)]))?;
+
+    let mut all_ast = parse(tokens, 0).map_err(TreeWalkError::Syntax)?;
+    resolve(&mut all_ast).map_err(TreeWalkError::Syntax)?;
+
+    for stmt in all_ast.iter() {
+        match stmt.eval(enviroments) {
+            Ok(()) => {}
+            Err(Unwind::Return(_)) => unreachable!("top-level return passed the resolver"),
+            Err(Unwind::Error(line, message)) => {
+                return Err(TreeWalkError::Runtime { line, message })
+            }
+        }
+    }
+    Ok(())
 }
 
-fn evaluate(expr: Expr) {
-    if let Expr::Grouping(group) = expr {
-        group;
+pub(super) fn report(line: usize, donde: &str, message: &str) -> String {
+    if donde.is_empty() {
+        format!("[line {}] Error: {}", line, message)
+    } else {
+        format!("[line {}] Error {}: {}", line, donde, message)
     }
 }
@@ -0,0 +1,201 @@
+//! The original tree-walk interpreter for Lox. It evaluates the syntax tree
+//! directly instead of compiling to bytecode, and is kept as a second engine
+//! to cross-check the VM against.
+
+mod ast;
+mod interpreter;
+mod parser;
+mod resolver;
+mod scanner;
+mod token;
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt, ptr,
+    rc::{Rc, Weak},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ast::{LoxNative, LoxVal};
+
+pub use interpreter::{run, TreeWalkError};
+
+/// One scope of variables. Scopes are shared: a block or call links to the
+/// scope around it, and a function value keeps the scope it was declared
+/// in alive (see [`Closure`]). The outermost scope holds the globals.
+#[derive(Default)]
+pub struct Enviroments {
+    enclosing: Option<Rc<RefCell<Enviroments>>>,
+    map: HashMap<String, LoxVal>,
+}
+
+impl Enviroments {
+    /// A global scope holding the built-in natives.
+    pub fn new() -> Self {
+        let mut globals = Self::default();
+        globals.define_native(LoxNative::new("clock", 0, |_| {
+            let elapsed = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_err(|_| "System clock is before the Unix epoch.".to_string())?;
+            Ok(LoxVal::Number(elapsed.as_secs_f64()))
+        }));
+        globals
+    }
+
+    /// Defines the `argc()` and `argv(i)` natives that let scripts read
+    /// `args`, the same as [`VM::set_args`](crate::VM::set_args).
+    pub fn set_args(&mut self, args: Vec<String>) {
+        let args = Rc::new(args);
+        let count = args.len();
+        self.define_native(LoxNative::new("argc", 0, move |_| {
+            Ok(LoxVal::Number(count as f64))
+        }));
+        self.define_native(LoxNative::new("argv", 1, move |arguments| {
+            let index = match arguments[0] {
+                LoxVal::Number(index) if index.fract() == 0.0 && index >= 0.0 => index as usize,
+                _ => return Err("Argument index must be a whole number.".to_string()),
+            };
+            match args.get(index) {
+                Some(arg) => Ok(LoxVal::String(arg.clone())),
+                None => Err("Argument index out of range.".to_string()),
+            }
+        }));
+    }
+
+    fn define_native(&mut self, native: LoxNative) {
+        self.define(native.name.to_string(), LoxVal::Native(Rc::new(native)));
+    }
+
+    fn new_enclosed(enclosing: &Rc<RefCell<Enviroments>>) -> Rc<RefCell<Enviroments>> {
+        Rc::new(RefCell::new(Self {
+            enclosing: Some(Rc::clone(enclosing)),
+            map: HashMap::new(),
+        }))
+    }
+
+    fn get(&self, id: &str) -> Option<LoxVal> {
+        self.map.get(id).map(|value| match value {
+            LoxVal::Functions(fun, closure) => {
+                LoxVal::Functions(Rc::clone(fun), Closure::Strong(closure.scope()))
+            }
+            value => value.clone(),
+        })
+    }
+
+    fn define(&mut self, key: String, value: LoxVal) {
+        let value = self.stored(value);
+        self.map.insert(key, value);
+    }
+
+    /// Assigns to `key` in this scope. Returns `false` if it has no such
+    /// variable.
+    fn assign(&mut self, key: &str, value: LoxVal) -> bool {
+        let value = self.stored(value);
+        match self.map.get_mut(key) {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// A function about to be stored in the scope it closes over only keeps
+    /// that scope weakly, so the two do not keep each other alive.
+    fn stored(&self, value: LoxVal) -> LoxVal {
+        match value {
+            LoxVal::Functions(fun, Closure::Strong(scope)) if ptr::eq(scope.as_ptr(), self) => {
+                LoxVal::Functions(fun, Closure::Weak(Rc::downgrade(&scope)))
+            }
+            value => value,
+        }
+    }
+
+    /// The scope `distance` hops out from `env`, as computed by the resolver.
+    fn ancestor(env: &Rc<RefCell<Enviroments>>, distance: usize) -> Rc<RefCell<Enviroments>> {
+        let mut scope = Rc::clone(env);
+        for _ in 0..distance {
+            let enclosing = scope
+                .borrow()
+                .enclosing
+                .clone()
+                .expect("resolved distance outside the scope chain");
+            scope = enclosing;
+        }
+        scope
+    }
+
+    /// The outermost scope, which holds the globals.
+    fn globals(env: &Rc<RefCell<Enviroments>>) -> Rc<RefCell<Enviroments>> {
+        let mut scope = Rc::clone(env);
+        loop {
+            let enclosing = scope.borrow().enclosing.clone();
+            match enclosing {
+                Some(enclosing) => scope = enclosing,
+                None => return scope,
+            }
+        }
+    }
+
+    fn get_at(env: &Rc<RefCell<Enviroments>>, distance: usize, id: &str) -> Option<LoxVal> {
+        Self::ancestor(env, distance).borrow().get(id)
+    }
+
+    fn assign_at(
+        env: &Rc<RefCell<Enviroments>>,
+        distance: usize,
+        key: &str,
+        value: LoxVal,
+    ) -> bool {
+        Self::ancestor(env, distance)
+            .borrow_mut()
+            .assign(key, value)
+    }
+}
+
+impl fmt::Debug for Enviroments {
+    // Function values hold on to the scope they were declared in, which
+    // usually holds the function itself, so the chain is not followed.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Enviroments")
+            .field("names", &self.map.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The scope a function value was declared in.
+///
+/// Declaring a function stores it in the very scope it closes over, which
+/// with two strong references would leak every call that declares an inner
+/// function. The copy held by that scope is weak instead, and turns strong
+/// again whenever it is read out, so a function that escapes still keeps
+/// its scope alive. Cycles through another scope or an instance field, such
+/// as a closure saved into an instance held by its own scope, still leak.
+#[derive(Debug, Clone)]
+pub enum Closure {
+    Strong(Rc<RefCell<Enviroments>>),
+    Weak(Weak<RefCell<Enviroments>>),
+}
+
+impl Closure {
+    fn scope(&self) -> Rc<RefCell<Enviroments>> {
+        match self {
+            Closure::Strong(scope) => Rc::clone(scope),
+            // Only the scope itself holds the weak copy, so it is still alive
+            // whenever the copy is reachable.
+            Closure::Weak(scope) => scope.upgrade().expect("closure outlived its scope"),
+        }
+    }
+
+    fn same_scope(&self, other: &Closure) -> bool {
+        ptr::eq(self.as_ptr(), other.as_ptr())
+    }
+
+    fn as_ptr(&self) -> *const RefCell<Enviroments> {
+        match self {
+            Closure::Strong(scope) => Rc::as_ptr(scope),
+            Closure::Weak(scope) => scope.as_ptr(),
+        }
+    }
+}
@@ -1,182 +1,175 @@
-use std::fmt::Debug;
+use std::rc::Rc;
 
-use crate::{
-    ast::{self, Expr, Stmt},
-    token::{self, Object, Token, TokenType},
+use super::{
+    ast::{Expr, FunctionDecl, LoxVal, Stmt},
+    interpreter::report,
+    token::{Object, Token, TokenType},
 };
 
-trait Prod {
-    type Input: Debug;
-}
+/// A syntax error, already formatted for the user.
+type ParseError = String;
 
-pub fn parse(tokens: Vec<Token>, mut pos: usize) -> Vec<Stmt> {
+pub fn parse(tokens: Vec<Token>, mut pos: usize) -> Result<Vec<Stmt>, Vec<ParseError>> {
     let mut stmt = Vec::new();
-
-    while pos < tokens.len() {
-        if let Some(stm) = declaration(&tokens, &mut pos) {
-            stmt.push(stm);
+    let mut errors = Vec::new();
+
+    while tokens[pos].token_type != TokenType::EOF {
+        match declaration(&tokens, &mut pos) {
+            Ok(stm) => stmt.push(stm),
+            Err(error) => {
+                errors.push(error);
+                synchronize(&tokens, &mut pos);
+            }
         }
     }
 
-    stmt
+    if errors.is_empty() {
+        Ok(stmt)
+    } else {
+        Err(errors)
+    }
 }
 
-fn declaration(tokens: &Vec<Token>, pos: &mut usize) -> Option<Stmt> {
+fn declaration(tokens: &[Token], pos: &mut usize) -> Result<Stmt, ParseError> {
     if let TokenType::VAR = tokens[*pos].token_type {
         *pos += 1;
         return var_declaraton(tokens, pos);
     }
 
-    return statements(tokens, pos);
+    statements(tokens, pos)
 }
 
-fn var_declaraton(tokens: &Vec<Token>, pos: &mut usize) -> Option<Stmt> {
-    let mut name = tokens[*pos].clone();
-    match consume(
-        TokenType::IDENTIFIER,
-        "Expect variable name.".to_string(),
-        tokens,
-        pos,
-    ) {
-        Ok(n) => name = n,
-        Err(_) => synchronize(tokens, pos),
-    };
+fn var_declaraton(tokens: &[Token], pos: &mut usize) -> Result<Stmt, ParseError> {
+    let name = consume(TokenType::IDENTIFIER, "Expect variable name.", tokens, pos)?;
 
     let mut initializer = None;
 
     if TokenType::EQUAL == tokens[*pos].token_type {
         *pos += 1;
-        initializer = Some(expression(tokens, pos).unwrap());
+        initializer = Some(expression(tokens, pos)?);
     }
     consume(
         TokenType::SEMICOLON,
-        "Expect ';' after variable declaration.".to_string(),
+        "Expect ';' after variable declaration.",
         tokens,
         pos,
-    );
+    )?;
 
-    Some(Stmt::VarDecl(name, initializer))
+    Ok(Stmt::VarDecl(name, initializer))
 }
 
-fn statements(tokens: &Vec<Token>, pos: &mut usize) -> Option<Stmt> {
+fn statements(tokens: &[Token], pos: &mut usize) -> Result<Stmt, ParseError> {
     if let TokenType::PRINT = tokens[*pos].token_type {
         *pos += 1;
-        return Some(print_stmt(tokens, pos));
+        return print_stmt(tokens, pos);
     }
 
     if let TokenType::RETURN = tokens[*pos].token_type {
         *pos += 1;
-        return Some(return_stmt(tokens, pos));
+        return return_stmt(tokens, pos);
     }
 
     if let TokenType::FUN = tokens[*pos].token_type {
         *pos += 1;
-        return Some(function_stmt(tokens, pos, "function".to_string()));
+        return function_stmt(tokens, pos, "function");
     }
 
     if let TokenType::LEFT_BRACE = tokens[*pos].token_type {
         *pos += 1;
-        return Some(block_stmt(tokens, pos));
+        return Ok(Stmt::Block(block_stmt(tokens, pos)?));
     }
 
     if let TokenType::IF = tokens[*pos].token_type {
         *pos += 1;
-        return Some(if_stmt(tokens, pos));
+        return if_stmt(tokens, pos);
     }
 
     if let TokenType::WHILE = tokens[*pos].token_type {
         *pos += 1;
-        return Some(while_stmt(tokens, pos));
+        return while_stmt(tokens, pos);
     }
 
     if let TokenType::FOR = tokens[*pos].token_type {
         *pos += 1;
-        return Some(for_stmt(tokens, pos));
+        return for_stmt(tokens, pos);
     }
 
     if let TokenType::CLASS = tokens[*pos].token_type {
         *pos += 1;
-        return Some(class_stmt(tokens, pos));
+        return class_stmt(tokens, pos);
     }
 
     expr_stmt(tokens, pos)
 }
 
-fn class_stmt(tokens: &Vec<Token>, pos: &mut usize) -> Stmt {
-    let name = consume(
-        TokenType::IDENTIFIER,
-        "Expect class name after ".to_string(),
-        tokens,
-        pos,
-    )
-    .unwrap();
+fn class_stmt(tokens: &[Token], pos: &mut usize) -> Result<Stmt, ParseError> {
+    let name = consume(TokenType::IDENTIFIER, "Expect class name.", tokens, pos)?;
 
     consume(
         TokenType::LEFT_BRACE,
-        "Expect LEFT BRACE after ".to_string(),
+        "Expect '{' before class body.",
         tokens,
         pos,
-    );
+    )?;
     let mut methods = Vec::new();
-    while tokens[*pos].token_type != TokenType::RIGHT_BRACE && *pos < tokens.len() {
-        methods.push(function_stmt(tokens, pos, "method".to_string()))
+    while tokens[*pos].token_type != TokenType::RIGHT_BRACE
+        && tokens[*pos].token_type != TokenType::EOF
+    {
+        methods.push(function_stmt(tokens, pos, "method")?)
     }
 
     consume(
         TokenType::RIGHT_BRACE,
-        "Expect LEFT BRACE after ".to_string(),
+        "Expect '}' after class body.",
         tokens,
         pos,
-    );
+    )?;
 
-    Stmt::ClassDcl(name, methods)
+    Ok(Stmt::ClassDcl(name, methods))
 }
-fn return_stmt(tokens: &Vec<Token>, pos: &mut usize) -> Stmt {
+fn return_stmt(tokens: &[Token], pos: &mut usize) -> Result<Stmt, ParseError> {
+    let keyword = tokens[*pos - 1].clone();
     let mut value: Option<Expr> = None;
     if tokens[*pos].token_type != TokenType::SEMICOLON {
-        value = expression(tokens, pos);
+        value = Some(expression(tokens, pos)?);
     }
     consume(
         TokenType::SEMICOLON,
-        "Expect 'SEMICOLON' after ".to_string(),
+        "Expect ';' after return value.",
         tokens,
         pos,
-    );
+    )?;
 
-    Stmt::Return(value)
+    Ok(Stmt::Return(keyword, value))
 }
-fn function_stmt(tokens: &Vec<Token>, pos: &mut usize, kind: String) -> Stmt {
+fn function_stmt(tokens: &[Token], pos: &mut usize, kind: &str) -> Result<Stmt, ParseError> {
     let name = consume(
         TokenType::IDENTIFIER,
-        "EXPECTED NAME".to_string(),
+        &format!("Expect {} name.", kind),
         tokens,
         pos,
-    )
-    .unwrap();
+    )?;
 
     consume(
         TokenType::LEFT_PAREN,
-        "Expect '(' after ".to_string(),
+        &format!("Expect '(' after {} name.", kind),
         tokens,
         pos,
-    );
+    )?;
 
     let mut params = Vec::new();
     if tokens[*pos].token_type != TokenType::RIGHT_PAREN {
         loop {
             if params.len() >= 255 {
-                panic!("Can't have more than 255 parameters.");
+                return Err(error(tokens, pos, "Can't have more than 255 parameters."));
             }
 
-            params.push(
-                consume(
-                    TokenType::IDENTIFIER,
-                    "EXPECTED NAME".to_string(),
-                    tokens,
-                    pos,
-                )
-                .unwrap(),
-            );
+            params.push(consume(
+                TokenType::IDENTIFIER,
+                "Expect parameter name.",
+                tokens,
+                pos,
+            )?);
 
             if tokens[*pos].token_type == TokenType::COMMA {
                 *pos += 1;
@@ -188,30 +181,34 @@ fn function_stmt(tokens: &Vec<Token>, pos: &mut usize, kind: String) -> Stmt {
 
     consume(
         TokenType::RIGHT_PAREN,
-        "Expect '(' after ".to_string(),
+        "Expect ')' after parameters.",
         tokens,
         pos,
-    );
+    )?;
 
     consume(
         TokenType::LEFT_BRACE,
-        "Expect '(' after ".to_string(),
+        &format!("Expect '{{' before {} body.", kind),
         tokens,
         pos,
-    );
+    )?;
 
-    let body = block_stmt(tokens, pos);
+    let body = block_stmt(tokens, pos)?;
 
-    Stmt::Functions(name, params, Box::new(body))
+    Ok(Stmt::Functions(Rc::new(FunctionDecl {
+        name,
+        params,
+        body,
+    })))
 }
 
-fn for_stmt(tokens: &Vec<Token>, pos: &mut usize) -> Stmt {
+fn for_stmt(tokens: &[Token], pos: &mut usize) -> Result<Stmt, ParseError> {
     consume(
         TokenType::LEFT_PAREN,
-        "Expect '(' after 'for'.".to_string(),
+        "Expect '(' after 'for'.",
         tokens,
         pos,
-    );
+    )?;
 
     let mut init: Option<Stmt> = None;
 
@@ -219,356 +216,276 @@ fn for_stmt(tokens: &Vec<Token>, pos: &mut usize) -> Stmt {
         *pos += 1;
     } else if let TokenType::VAR = tokens[*pos].token_type {
         *pos += 1;
-        init = var_declaraton(tokens, pos);
+        init = Some(var_declaraton(tokens, pos)?);
     } else {
-        init = expr_stmt(tokens, pos);
+        init = Some(expr_stmt(tokens, pos)?);
     }
 
     let mut condition: Option<Expr> = None;
 
     if TokenType::SEMICOLON != tokens[*pos].token_type {
-        condition = expression(tokens, pos);
+        condition = Some(expression(tokens, pos)?);
     }
 
     consume(
         TokenType::SEMICOLON,
-        "Expect ';' after 'for'.".to_string(),
+        "Expect ';' after loop condition.",
         tokens,
         pos,
-    );
+    )?;
 
     let mut increment: Option<Expr> = None;
 
     if TokenType::RIGHT_PAREN != tokens[*pos].token_type {
-        increment = expression(tokens, pos);
+        increment = Some(expression(tokens, pos)?);
     }
 
     consume(
         TokenType::RIGHT_PAREN,
-        "Expect ';' after 'for'.".to_string(),
+        "Expect ')' after for clauses.",
         tokens,
         pos,
-    );
+    )?;
 
-    let mut body = statements(tokens, pos).unwrap();
+    let mut body = statements(tokens, pos)?;
 
     if let Some(inc) = increment {
         body = Stmt::Block(vec![body, Stmt::ExprStmt(inc)])
     }
 
-    if let None = condition {
-        condition = Some(Expr::Literal(ast::LoxVal::Boolean(true)));
-    }
-    body = Stmt::WhileStmt(condition.unwrap(), Some(Box::new(body)));
+    let condition = condition.unwrap_or(Expr::Literal(LoxVal::Boolean(true)));
+    body = Stmt::WhileStmt(condition, Some(Box::new(body)));
 
     if let Some(initi) = init {
         body = Stmt::Block(vec![initi, body]);
     }
 
-    return body;
+    Ok(body)
 }
 
-fn while_stmt(tokens: &Vec<Token>, pos: &mut usize) -> Stmt {
+fn while_stmt(tokens: &[Token], pos: &mut usize) -> Result<Stmt, ParseError> {
     consume(
         TokenType::LEFT_PAREN,
-        "Expect '(' after 'while'.".to_string(),
+        "Expect '(' after 'while'.",
         tokens,
         pos,
-    );
+    )?;
 
-    let condition = expression(tokens, pos).unwrap();
+    let condition = expression(tokens, pos)?;
 
     consume(
         TokenType::RIGHT_PAREN,
-        "Expect '(' after 'if'.".to_string(),
+        "Expect ')' after condition.",
         tokens,
         pos,
-    );
+    )?;
 
-    let body = match statements(tokens, pos) {
-        Some(b) => Some(Box::new(b)),
-        None => None,
-    };
+    let body = statements(tokens, pos)?;
 
-    Stmt::WhileStmt(condition, body)
+    Ok(Stmt::WhileStmt(condition, Some(Box::new(body))))
 }
 
-fn if_stmt(tokens: &Vec<Token>, pos: &mut usize) -> Stmt {
-    consume(
-        TokenType::LEFT_PAREN,
-        "Expect '(' after 'if'.".to_string(),
-        tokens,
-        pos,
-    );
-    let condition = expression(tokens, pos).unwrap();
+fn if_stmt(tokens: &[Token], pos: &mut usize) -> Result<Stmt, ParseError> {
+    consume(TokenType::LEFT_PAREN, "Expect '(' after 'if'.", tokens, pos)?;
+    let condition = expression(tokens, pos)?;
     consume(
         TokenType::RIGHT_PAREN,
-        "Expect '(' after 'if'.".to_string(),
+        "Expect ')' after if condition.",
         tokens,
         pos,
-    );
+    )?;
 
-    let then_branch = statements(tokens, pos).unwrap();
+    let then_branch = statements(tokens, pos)?;
 
     let mut else_branch: Option<Box<Stmt>> = None;
 
     if let TokenType::ELSE = tokens[*pos].token_type {
         *pos += 1;
-        else_branch = Some(Box::new(statements(tokens, pos).unwrap()));
+        else_branch = Some(Box::new(statements(tokens, pos)?));
     }
 
-    return Stmt::IfStmt(condition, Box::new(then_branch), else_branch);
+    Ok(Stmt::IfStmt(condition, Box::new(then_branch), else_branch))
 }
 
-fn block_stmt(tokens: &Vec<Token>, pos: &mut usize) -> Stmt {
+fn block_stmt(tokens: &[Token], pos: &mut usize) -> Result<Vec<Stmt>, ParseError> {
     let mut block = Vec::new();
-    while *pos < tokens.len()
-        && tokens[*pos].token_type != TokenType::EOF
+    while tokens[*pos].token_type != TokenType::EOF
         && tokens[*pos].token_type != TokenType::RIGHT_BRACE
     {
-        match declaration(tokens, pos) {
-            Some(decl) => block.push(decl),
-            None => {}
-        }
+        block.push(declaration(tokens, pos)?);
     }
     consume(
         TokenType::RIGHT_BRACE,
-        "Expect '}' after block.".to_string(),
+        "Expect '}' after block.",
         tokens,
         pos,
-    );
-    Stmt::Block(block)
+    )?;
+    Ok(block)
 }
-fn print_stmt(tokens: &Vec<Token>, pos: &mut usize) -> Stmt {
-    let val = parse_expr(tokens, pos);
+fn print_stmt(tokens: &[Token], pos: &mut usize) -> Result<Stmt, ParseError> {
+    let val = expression(tokens, pos)?;
 
-    match consume(
-        TokenType::SEMICOLON,
-        "Expected ';' after value".to_string(),
-        tokens,
-        pos,
-    ) {
-        Ok(_) => {}
-        Err(_) => {
-            panic!("print stmt hey");
-        }
-    }
+    consume(TokenType::SEMICOLON, "Expect ';' after value.", tokens, pos)?;
 
-    if val.is_none() {
-        panic!("ERROR");
-    }
-    Stmt::PrintStmt(val.unwrap())
+    Ok(Stmt::PrintStmt(val))
 }
 
-fn expr_stmt(tokens: &Vec<Token>, pos: &mut usize) -> Option<Stmt> {
-    let expr = parse_expr(tokens, pos);
-    if expr.is_none() {
-        *pos += 1;
-        return None;
-    }
-    match consume(
+fn expr_stmt(tokens: &[Token], pos: &mut usize) -> Result<Stmt, ParseError> {
+    let expr = expression(tokens, pos)?;
+    consume(
         TokenType::SEMICOLON,
-        "Expected ';' after value".to_string(),
+        "Expect ';' after expression.",
         tokens,
         pos,
-    ) {
-        Ok(_) => {}
-        Err(_) => {
-            panic!("exprr stmt hey");
-        }
-    }
-
-    Some(Stmt::ExprStmt(expr.unwrap()))
-}
+    )?;
 
-fn parse_expr(tokens: &Vec<Token>, pos: &mut usize) -> Option<Expr> {
-    expression(tokens, pos)
+    Ok(Stmt::ExprStmt(expr))
 }
 
-fn expression(tokens: &Vec<Token>, pos: &mut usize) -> Option<Expr> {
+fn expression(tokens: &[Token], pos: &mut usize) -> Result<Expr, ParseError> {
     assignment(tokens, pos)
 }
 
-fn assignment(tokens: &Vec<Token>, pos: &mut usize) -> Option<Expr> {
-    let expr = or(tokens, pos);
+fn assignment(tokens: &[Token], pos: &mut usize) -> Result<Expr, ParseError> {
+    let expr = or(tokens, pos)?;
 
     if let TokenType::EQUAL = tokens[*pos].token_type {
+        let equals = *pos;
         *pos += 1;
-        let equals = tokens[*pos - 1].clone();
-        let value = assignment(tokens, pos);
+        let value = assignment(tokens, pos)?;
 
-        if let Expr::Variable(name) = expr.clone().unwrap() {
-            return Some(Expr::Assign(name, Box::new(value.unwrap())));
-        } else if let Expr::Get(obj, name) = expr.clone().unwrap() {
-            return Some(Expr::Set(obj, name, Box::new(value.unwrap())));
-        }
-        error(tokens, pos, "Invalid assignment target.".to_string());
+        return match expr {
+            Expr::Variable(name, _) => Ok(Expr::Assign(name, Box::new(value), None)),
+            Expr::Get(obj, name) => Ok(Expr::Set(obj, name, Box::new(value))),
+            _ => Err(error(tokens, &equals, "Invalid assignment target.")),
+        };
     }
 
-    return expr;
+    Ok(expr)
 }
 
-fn or(tokens: &Vec<Token>, pos: &mut usize) -> Option<Expr> {
-    let mut expr: Option<Expr> = and(tokens, pos);
+fn or(tokens: &[Token], pos: &mut usize) -> Result<Expr, ParseError> {
+    let mut expr = and(tokens, pos)?;
 
     while tokens[*pos].token_type == TokenType::OR {
         *pos += 1;
         let operator = tokens[*pos - 1].clone();
-        let right = and(tokens, pos);
-        expr = Some(Expr::Logical(
-            Box::new(expr.unwrap()),
-            operator,
-            Box::new(right.unwrap()),
-        ));
+        let right = and(tokens, pos)?;
+        expr = Expr::Logical(Box::new(expr), operator, Box::new(right));
     }
 
-    expr
+    Ok(expr)
 }
 
-fn and(tokens: &Vec<Token>, pos: &mut usize) -> Option<Expr> {
-    let mut expr = equality(tokens, pos);
+fn and(tokens: &[Token], pos: &mut usize) -> Result<Expr, ParseError> {
+    let mut expr = equality(tokens, pos)?;
 
     while tokens[*pos].token_type == TokenType::AND {
         *pos += 1;
 
         let operator = tokens[*pos - 1].clone();
-        let right = equality(tokens, pos);
+        let right = equality(tokens, pos)?;
 
-        expr = Some(Expr::Logical(
-            Box::new(expr.unwrap()),
-            operator,
-            Box::new(right.unwrap()),
-        ));
+        expr = Expr::Logical(Box::new(expr), operator, Box::new(right));
     }
 
-    expr
+    Ok(expr)
 }
 
-fn equality(tokens: &Vec<Token>, pos: &mut usize) -> Option<Expr> {
-    if tokens[*pos].token_type == TokenType::EOF {
-        return None;
-    }
-    let mut expr = comparison(tokens, pos);
+fn equality(tokens: &[Token], pos: &mut usize) -> Result<Expr, ParseError> {
+    let mut expr = comparison(tokens, pos)?;
 
-    while *pos < tokens.len() && tokens[*pos].token_type != TokenType::EOF {
-        match tokens[*pos].token_type {
-            TokenType::BANG_EQUAL | TokenType::EQUAL_EQUAL => {
-                *pos += 1;
-                let operator = tokens[*pos - 1].clone();
-                let right = comparison(tokens, pos);
-                expr = ast::Expr::Binary(Box::new(expr.clone()), operator, Box::new(right));
-            }
-            _ => {
-                break;
-            }
-        }
+    while let TokenType::BANG_EQUAL | TokenType::EQUAL_EQUAL = tokens[*pos].token_type {
+        *pos += 1;
+        let operator = tokens[*pos - 1].clone();
+        let right = comparison(tokens, pos)?;
+        expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
     }
-    Some(expr)
+    Ok(expr)
 }
 
-fn comparison(tokens: &Vec<Token>, pos: &mut usize) -> Expr {
-    let mut expr = term(tokens, pos);
+fn comparison(tokens: &[Token], pos: &mut usize) -> Result<Expr, ParseError> {
+    let mut expr = term(tokens, pos)?;
 
-    while *pos < tokens.len() && tokens[*pos].token_type != TokenType::EOF {
-        match tokens[*pos].token_type {
-            TokenType::GREATER
-            | TokenType::GREATER_EQUAL
-            | TokenType::LESS
-            | TokenType::LESS_EQUAL => {
-                *pos += 1;
-                let operator = tokens[*pos - 1].clone();
-                let right = term(tokens, pos);
-                expr = ast::Expr::Binary(Box::new(expr.clone()), operator, Box::new(right));
-            }
-            _ => {
-                break;
-            }
-        }
+    while let TokenType::GREATER
+    | TokenType::GREATER_EQUAL
+    | TokenType::LESS
+    | TokenType::LESS_EQUAL = tokens[*pos].token_type
+    {
+        *pos += 1;
+        let operator = tokens[*pos - 1].clone();
+        let right = term(tokens, pos)?;
+        expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
     }
-    expr
+    Ok(expr)
 }
 
-fn term(tokens: &Vec<Token>, pos: &mut usize) -> Expr {
-    let mut expr = factor(tokens, pos);
+fn term(tokens: &[Token], pos: &mut usize) -> Result<Expr, ParseError> {
+    let mut expr = factor(tokens, pos)?;
 
-    while *pos < tokens.len() && tokens[*pos].token_type != TokenType::EOF {
-        match tokens[*pos].token_type {
-            TokenType::MINUS | TokenType::PLUS => {
-                *pos += 1;
-                let operator = tokens[*pos - 1].clone();
-                let right = factor(tokens, pos);
-                expr = ast::Expr::Binary(Box::new(expr.clone()), operator, Box::new(right));
-            }
-            _ => {
-                break;
-            }
-        }
+    while let TokenType::MINUS | TokenType::PLUS = tokens[*pos].token_type {
+        *pos += 1;
+        let operator = tokens[*pos - 1].clone();
+        let right = factor(tokens, pos)?;
+        expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
     }
-    expr
+    Ok(expr)
 }
-fn factor(tokens: &Vec<Token>, pos: &mut usize) -> Expr {
-    let mut expr = unary(tokens, pos);
+fn factor(tokens: &[Token], pos: &mut usize) -> Result<Expr, ParseError> {
+    let mut expr = unary(tokens, pos)?;
 
-    while *pos < tokens.len() && tokens[*pos].token_type != TokenType::EOF {
-        match tokens[*pos].token_type {
-            TokenType::SLASH | TokenType::STAR => {
-                *pos += 1;
-                let operator = tokens[*pos - 1].clone();
-                let right = unary(tokens, pos);
-                expr = ast::Expr::Binary(Box::new(expr.clone()), operator, Box::new(right));
-            }
-            _ => {
-                break;
-            }
-        }
+    while let TokenType::SLASH | TokenType::STAR = tokens[*pos].token_type {
+        *pos += 1;
+        let operator = tokens[*pos - 1].clone();
+        let right = unary(tokens, pos)?;
+        expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
     }
-    expr
+    Ok(expr)
 }
 
-fn unary(tokens: &Vec<Token>, pos: &mut usize) -> Expr {
+fn unary(tokens: &[Token], pos: &mut usize) -> Result<Expr, ParseError> {
     match tokens[*pos].token_type {
         TokenType::BANG | TokenType::MINUS => {
             *pos += 1;
             let operator = tokens[*pos - 1].clone();
-            let right = unary(tokens, pos);
-            ast::Expr::Unary(operator, Box::new(right))
+            let right = unary(tokens, pos)?;
+            Ok(Expr::Unary(operator, Box::new(right)))
         }
         _ => call(tokens, pos),
     }
 }
 
-fn call(tokens: &Vec<Token>, pos: &mut usize) -> Expr {
-    let mut expr = primary(tokens, pos);
+fn call(tokens: &[Token], pos: &mut usize) -> Result<Expr, ParseError> {
+    let mut expr = primary(tokens, pos)?;
 
     loop {
         if tokens[*pos].token_type == TokenType::LEFT_PAREN {
             *pos += 1;
-            expr = finish_call(expr, tokens, pos);
+            expr = finish_call(expr, tokens, pos)?;
         } else if tokens[*pos].token_type == TokenType::DOT {
             *pos += 1;
             let name = consume(
                 TokenType::IDENTIFIER,
-                "Expected property name".to_string(),
+                "Expect property name after '.'.",
                 tokens,
                 pos,
-            )
-            .unwrap();
+            )?;
             expr = Expr::Get(Box::new(expr), name);
         } else {
             break;
         }
     }
 
-    expr
+    Ok(expr)
 }
-fn finish_call(callee: Expr, tokens: &Vec<Token>, pos: &mut usize) -> Expr {
+fn finish_call(callee: Expr, tokens: &[Token], pos: &mut usize) -> Result<Expr, ParseError> {
     let mut arguments = Vec::new();
     if tokens[*pos].token_type != TokenType::RIGHT_PAREN {
         loop {
-            if arguments.len() > 255 {
-                panic!("NO more than 255");
+            if arguments.len() >= 255 {
+                return Err(error(tokens, pos, "Can't have more than 255 arguments."));
             }
-            arguments.push(expression(tokens, pos));
+            arguments.push(expression(tokens, pos)?);
 
             if tokens[*pos].token_type == TokenType::COMMA {
                 *pos += 1;
@@ -580,119 +497,89 @@ fn finish_call(callee: Expr, tokens: &Vec<Token>, pos: &mut usize) -> Expr {
 
     let paren = consume(
         TokenType::RIGHT_PAREN,
-        "Expect ')' after arguments.".to_string(),
+        "Expect ')' after arguments.",
         tokens,
         pos,
-    )
-    .unwrap();
+    )?;
 
-    Expr::Call(Box::new(callee), paren, arguments)
+    Ok(Expr::Call(Box::new(callee), paren, arguments))
 }
-fn primary(tokens: &Vec<Token>, pos: &mut usize) -> Expr {
-    match tokens[*pos].token_type {
-        TokenType::FALSE => {
-            *pos += 1;
-            ast::Expr::Literal(ast::LoxVal::Boolean(false))
-        }
-        TokenType::TRUE => {
-            *pos += 1;
-            ast::Expr::Literal(ast::LoxVal::Boolean(true))
-        }
-        TokenType::NIL => {
-            *pos += 1;
-            ast::Expr::Literal(ast::LoxVal::Nil)
-        }
-
-        TokenType::NUMBER => {
-            *pos += 1;
-            if let Object::Number(num) = tokens[*pos - 1].literal {
-                ast::Expr::Literal(ast::LoxVal::Number(num))
-            } else {
-                panic!("what");
-            }
-        }
-        TokenType::STRING => {
-            *pos += 1;
-            if let Object::String(word) = tokens[*pos - 1].literal.clone() {
-                ast::Expr::Literal(ast::LoxVal::String(word))
-            } else {
-                panic!("what");
-            }
-        }
-
+fn primary(tokens: &[Token], pos: &mut usize) -> Result<Expr, ParseError> {
+    let expr = match tokens[*pos].token_type {
+        TokenType::FALSE => Expr::Literal(LoxVal::Boolean(false)),
+        TokenType::TRUE => Expr::Literal(LoxVal::Boolean(true)),
+        TokenType::NIL => Expr::Literal(LoxVal::Nil),
+        TokenType::NUMBER => match tokens[*pos].literal {
+            Object::Number(num) => Expr::Literal(LoxVal::Number(num)),
+            _ => unreachable!("number token without a number literal"),
+        },
+        TokenType::STRING => match &tokens[*pos].literal {
+            Object::String(word) => Expr::Literal(LoxVal::String(word.clone())),
+            _ => unreachable!("string token without a string literal"),
+        },
         TokenType::LEFT_PAREN => {
             *pos += 1;
-            let expr = expression(tokens, pos).unwrap();
-            match consume(
+            let expr = expression(tokens, pos)?;
+            consume(
                 TokenType::RIGHT_PAREN,
-                "Expected ')' after expresion".to_string(),
+                "Expect ')' after expression.",
                 tokens,
                 pos,
-            ) {
-                Ok(_) => {}
-                Err(_) => panic!("print right param"),
-            }
-            ast::Expr::Grouping(Box::new(expr))
-        }
-
-        TokenType::IDENTIFIER => {
-            *pos += 1;
-            ast::Expr::Variable(tokens[*pos - 1].clone())
+            )?;
+            return Ok(Expr::Grouping(Box::new(expr)));
         }
+        TokenType::IDENTIFIER => Expr::Variable(tokens[*pos].clone(), None),
+        _ => return Err(error(tokens, pos, "Expect expression.")),
+    };
 
-        _ => {
-            println!("{:?}", tokens[*pos]);
-            panic!("why")
-        }
-    }
+    *pos += 1;
+    Ok(expr)
 }
 
 fn consume(
     tipo: TokenType,
-    message: String,
-    tokens: &Vec<Token>,
+    message: &str,
+    tokens: &[Token],
     pos: &mut usize,
-) -> Result<Token, String> {
+) -> Result<Token, ParseError> {
     if tipo == tokens[*pos].token_type {
         *pos += 1;
         Ok(tokens[*pos - 1].clone())
     } else {
-        println!("NOES SEMICOLON {:?}", tokens[*pos]);
-        error(tokens, pos, message)
+        Err(error(tokens, pos, message))
     }
 }
 
-fn error(tokens: &Vec<Token>, pos: &mut usize, message: String) -> Result<Token, String> {
-    if tokens[*pos].token_type == TokenType::EOF {
-        Err(format!("{} at end {}", tokens[*pos].line, message).to_string())
+fn error(tokens: &[Token], pos: &usize, message: &str) -> ParseError {
+    let token = &tokens[*pos];
+    if token.token_type == TokenType::EOF {
+        report(token.line, "at end", message)
     } else {
-        Err(format!(
-            "{} at  {} {}",
-            tokens[*pos].line, tokens[*pos].lexeme, message
-        )
-        .to_string())
+        report(token.line, &format!("at '{}'", token.lexeme), message)
     }
 }
 
-fn synchronize(tokens: &Vec<Token>, pos: &mut usize) {
-    *pos += 1;
+/// Skips tokens until a likely statement boundary so parsing can resume
+/// after an error.
+fn synchronize(tokens: &[Token], pos: &mut usize) {
+    if tokens[*pos].token_type != TokenType::EOF {
+        *pos += 1;
+    }
 
-    while *pos != tokens.len() - 1 {
+    while tokens[*pos].token_type != TokenType::EOF {
         if tokens[*pos - 1].token_type == TokenType::SEMICOLON {
             return;
         }
 
         match tokens[*pos].token_type {
-            TokenType::CLASS => {}
-            TokenType::FUN => {}
-            TokenType::VAR => {}
-            TokenType::FOR => {}
-            TokenType::IF => {}
-            TokenType::WHILE => {}
-            TokenType::PRINT => {}
-            TokenType::RETURN => {
-                return;
-            }
+            TokenType::CLASS
+            | TokenType::FUN
+            | TokenType::VAR
+            | TokenType::FOR
+            | TokenType::IF
+            | TokenType::WHILE
+            | TokenType::PRINT
+            | TokenType::RETURN => return,
             _ => {}
         }
         *pos += 1;
@@ -0,0 +1,360 @@
+use std::{collections::HashMap, rc::Rc};
+
+use super::{
+    ast::{Expr, FunctionDecl, Stmt},
+    interpreter::report,
+    token::Token,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+}
+
+/// A pass over the syntax tree, run before it is evaluated, that works out
+/// how many scopes out each local variable reference's declaration lives.
+/// It mirrors the scopes the interpreter creates at runtime: one per block
+/// and one per function call holding the parameters and the body.
+struct Resolver {
+    /// Locals declared in each enclosing block or function, innermost last.
+    /// The flag is `false` while the variable's initializer is running.
+    /// Globals are not tracked.
+    scopes: Vec<HashMap<String, bool>>,
+    current_function: FunctionType,
+    errors: Vec<String>,
+}
+
+/// Fills in the scope distance of every local variable reference in
+/// `statements`. References left without one are globals.
+pub fn resolve(statements: &mut [Stmt]) -> Result<(), Vec<String>> {
+    let mut resolver = Resolver {
+        scopes: Vec::new(),
+        current_function: FunctionType::None,
+        errors: Vec::new(),
+    };
+
+    for stmt in statements {
+        resolver.resolve_stmt(stmt);
+    }
+
+    if resolver.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(resolver.errors)
+    }
+}
+
+/// Function declarations only get shared once the code runs, so while it is
+/// being resolved the syntax tree still owns them outright.
+fn unshared(fun: &mut Rc<FunctionDecl>) -> &mut FunctionDecl {
+    Rc::get_mut(fun).expect("function declaration shared before it was resolved")
+}
+
+impl Resolver {
+    fn resolve_stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::Block(block) => {
+                self.begin_scope();
+                for stmt in block {
+                    self.resolve_stmt(stmt);
+                }
+                self.end_scope();
+            }
+            Stmt::VarDecl(name, init) => {
+                self.declare(name);
+                if let Some(init) = init {
+                    self.resolve_expr(init);
+                }
+                self.define(name);
+            }
+            Stmt::Functions(fun) => {
+                let fun = unshared(fun);
+                self.declare(&fun.name);
+                self.define(&fun.name);
+                self.resolve_function(fun);
+            }
+            Stmt::ClassDcl(name, methods) => {
+                self.declare(name);
+                self.define(name);
+                for method in methods {
+                    if let Stmt::Functions(fun) = method {
+                        self.resolve_function(unshared(fun));
+                    }
+                }
+            }
+            Stmt::ExprStmt(expr) | Stmt::PrintStmt(expr) => self.resolve_expr(expr),
+            Stmt::IfStmt(cond, then, else_stmt) => {
+                self.resolve_expr(cond);
+                self.resolve_stmt(then);
+                if let Some(else_b) = else_stmt {
+                    self.resolve_stmt(else_b);
+                }
+            }
+            Stmt::WhileStmt(condition, body) => {
+                self.resolve_expr(condition);
+                if let Some(body) = body {
+                    self.resolve_stmt(body);
+                }
+            }
+            Stmt::Return(keyword, value) => {
+                if self.current_function == FunctionType::None {
+                    self.error(keyword, "Can't return from top-level code.");
+                }
+                if let Some(value) = value {
+                    self.resolve_expr(value);
+                }
+            }
+        }
+    }
+
+    fn resolve_expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Variable(name, depth) => {
+                if let Some(false) = self.scopes.last().and_then(|scope| scope.get(&name.lexeme)) {
+                    self.error(name, "Can't read local variable in its own initializer.");
+                }
+                *depth = self.resolve_local(name);
+            }
+            Expr::Assign(name, value, depth) => {
+                self.resolve_expr(value);
+                *depth = self.resolve_local(name);
+            }
+            Expr::Literal(_) => {}
+            Expr::Unary(_, right) | Expr::Grouping(right) | Expr::Get(right, _) => {
+                self.resolve_expr(right)
+            }
+            Expr::Binary(left, _, right) | Expr::Logical(left, _, right) => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            Expr::Set(obj, _, value) => {
+                self.resolve_expr(value);
+                self.resolve_expr(obj);
+            }
+            Expr::Call(callee, _, arguments) => {
+                self.resolve_expr(callee);
+                for argument in arguments {
+                    self.resolve_expr(argument);
+                }
+            }
+        }
+    }
+
+    fn resolve_function(&mut self, fun: &mut FunctionDecl) {
+        let enclosing_function = self.current_function;
+        self.current_function = FunctionType::Function;
+
+        self.begin_scope();
+        for param in &fun.params {
+            self.declare(param);
+            self.define(param);
+        }
+        for stmt in &mut fun.body {
+            self.resolve_stmt(stmt);
+        }
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+    }
+
+    /// Number of scopes between the innermost one and the one declaring
+    /// `name`, or `None` if it is a global.
+    fn resolve_local(&self, name: &Token) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&name.lexeme))
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        if scope.insert(name.lexeme.clone(), false).is_some() {
+            self.error(name, "Already a variable with this name in this scope.");
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), true);
+        }
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.errors.push(report(
+            token.line,
+            &format!("at '{}'", token.lexeme),
+            message,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree_walk::{parser::parse, scanner::Scanner};
+
+    fn resolved(source: &str) -> Result<Vec<Stmt>, Vec<String>> {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        let mut statements = parse(tokens, 0).unwrap();
+        resolve(&mut statements)?;
+        Ok(statements)
+    }
+
+    /// Every variable read or assignment in `source`, in order, with the
+    /// scope distance the resolver gave it.
+    fn distances(source: &str) -> Vec<(String, Option<usize>)> {
+        fn walk_stmt(stmt: &Stmt, out: &mut Vec<(String, Option<usize>)>) {
+            match stmt {
+                Stmt::ExprStmt(e) | Stmt::PrintStmt(e) | Stmt::VarDecl(_, Some(e)) => {
+                    walk_expr(e, out)
+                }
+                Stmt::Return(_, Some(e)) => walk_expr(e, out),
+                Stmt::VarDecl(_, None) | Stmt::Return(_, None) => {}
+                Stmt::Block(body) => body.iter().for_each(|s| walk_stmt(s, out)),
+                Stmt::IfStmt(cond, then, else_stmt) => {
+                    walk_expr(cond, out);
+                    walk_stmt(then, out);
+                    else_stmt.iter().for_each(|s| walk_stmt(s, out));
+                }
+                Stmt::WhileStmt(cond, body) => {
+                    walk_expr(cond, out);
+                    body.iter().for_each(|s| walk_stmt(s, out));
+                }
+                Stmt::Functions(fun) => fun.body.iter().for_each(|s| walk_stmt(s, out)),
+                Stmt::ClassDcl(_, methods) => methods.iter().for_each(|s| walk_stmt(s, out)),
+            }
+        }
+        fn walk_expr(e: &Expr, out: &mut Vec<(String, Option<usize>)>) {
+            match e {
+                Expr::Variable(name, depth) => out.push((name.lexeme.clone(), *depth)),
+                Expr::Assign(name, value, depth) => {
+                    walk_expr(value, out);
+                    out.push((name.lexeme.clone(), *depth));
+                }
+                Expr::Literal(_) => {}
+                Expr::Unary(_, e) | Expr::Grouping(e) | Expr::Get(e, _) => walk_expr(e, out),
+                Expr::Binary(l, _, r) | Expr::Logical(l, _, r) | Expr::Set(l, _, r) => {
+                    walk_expr(l, out);
+                    walk_expr(r, out);
+                }
+                Expr::Call(callee, _, arguments) => {
+                    walk_expr(callee, out);
+                    arguments.iter().for_each(|a| walk_expr(a, out));
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        for s in &resolved(source).unwrap() {
+            walk_stmt(s, &mut out);
+        }
+        out
+    }
+
+    fn var(name: &str, depth: Option<usize>) -> (String, Option<usize>) {
+        (name.to_string(), depth)
+    }
+
+    #[test]
+    fn globals_are_left_unresolved() {
+        assert_eq!(
+            distances("var a = 1; a = a + 1;"),
+            [var("a", None), var("a", None)]
+        );
+    }
+
+    #[test]
+    fn locals_resolve_to_the_scope_declaring_them() {
+        let source = "{ var a = 1; { var b = a; { print a + b; a = 2; } } }";
+        assert_eq!(
+            distances(source),
+            [
+                var("a", Some(1)),
+                var("a", Some(2)),
+                var("b", Some(1)),
+                var("a", Some(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn closures_bind_to_the_declaration_in_scope_where_they_are_written() {
+        // The classic closure-binding test: `showA` must keep reading the
+        // global even after a local `a` is declared in the same block.
+        let source = r#"
+var a = "global";
+{
+  fun showA() { print a; }
+  showA();
+  var a = "block";
+  showA();
+}
+"#;
+        assert_eq!(
+            distances(source),
+            [var("a", None), var("showA", Some(0)), var("showA", Some(0))]
+        );
+    }
+
+    #[test]
+    fn functions_see_parameters_and_captured_locals() {
+        let source = r#"
+fun makeCounter() {
+  var i = 0;
+  fun count(step) {
+    i = i + step;
+    return i;
+  }
+  return count;
+}
+"#;
+        assert_eq!(
+            distances(source),
+            [
+                var("i", Some(1)),
+                var("step", Some(0)),
+                var("i", Some(1)),
+                var("i", Some(1)),
+                var("count", Some(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_return_at_top_level() {
+        assert_eq!(
+            resolved("return 1;").unwrap_err(),
+            ["[line 1] Error at 'return': Can't return from top-level code."]
+        );
+    }
+
+    #[test]
+    fn reports_local_read_in_its_own_initializer() {
+        assert_eq!(
+            resolved("{ var a = 1;\n  { var a = a; } }").unwrap_err(),
+            ["[line 2] Error at 'a': Can't read local variable in its own initializer."]
+        );
+        // Globals may refer to an earlier global of the same name.
+        assert!(resolved("var a = 1; var a = a;").is_ok());
+    }
+
+    #[test]
+    fn reports_duplicate_locals() {
+        assert_eq!(
+            resolved("fun f(a) { var a; }").unwrap_err(),
+            ["[line 1] Error at 'a': Already a variable with this name in this scope."]
+        );
+        assert!(resolved("var a; var a;").is_ok());
+    }
+}
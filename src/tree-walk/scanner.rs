@@ -1,6 +1,4 @@
-use crate::token::{Object, TokenType};
-
-use super::token::Token;
+use super::token::{Object, Token, TokenType};
 
 static KEYWORDS: [(&str, TokenType); 16] = [
     ("and", TokenType::AND),
@@ -44,8 +42,12 @@ impl<'a> Scanner<'a> {
             self.scan_token()?;
         }
 
-        self.tokens
-            .push(Token::new(TokenType::EOF, "".to_string(), Object::Null, 0));
+        self.tokens.push(Token::new(
+            TokenType::EOF,
+            "".to_string(),
+            Object::Null,
+            self.line,
+        ));
 
         Ok(self.tokens.clone())
     }
@@ -129,7 +131,7 @@ impl<'a> Scanner<'a> {
     }
 
     fn identifier(&mut self) -> Result<(), (usize, String)> {
-        while self.peek().is_ascii_alphanumeric() {
+        while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
             self.advance();
         }
 
@@ -150,7 +152,7 @@ impl<'a> Scanner<'a> {
         }
 
         if self.is_at_end() {
-            return Err((self.line, "Unterminated String".to_string()));
+            return Err((self.line, "Unterminated string.".to_string()));
         }
 
         self.advance();
@@ -182,22 +184,25 @@ impl<'a> Scanner<'a> {
         Ok(())
     }
 
+    // Reads the source a byte at a time, like the VM's scanner does.
+
     fn peek(&mut self) -> char {
-        if self.is_at_end() {
-            return '\0';
-        }
-        self.source.chars().nth(self.current).unwrap()
+        self.byte_at(self.current)
     }
 
     fn peek_next(&mut self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
+        self.byte_at(self.current + 1)
+    }
+
+    fn byte_at(&self, index: usize) -> char {
+        match self.source.as_bytes().get(index) {
+            Some(&byte) => byte as char,
+            None => '\0',
         }
-        self.source.chars().nth(self.current + 1).unwrap()
     }
 
     fn advance(&mut self) -> char {
-        let ch = self.source.chars().nth(self.current).unwrap();
+        let ch = self.byte_at(self.current);
         self.current += 1;
         ch
     }
@@ -218,7 +223,7 @@ impl<'a> Scanner<'a> {
             return false;
         }
 
-        if self.source.chars().nth(self.current).unwrap() != expected {
+        if self.peek() != expected {
             return false;
         }
 
@@ -1,4 +1,31 @@
-pub type Value = f64;
+use std::fmt;
+
+use crate::object::Obj;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Nil,
+    Number(f64),
+    Obj(Obj),
+}
+
+impl Value {
+    pub fn is_falsey(&self) -> bool {
+        matches!(self, Value::Nil | Value::Bool(false))
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Bool(boolean) => write!(f, "{}", boolean),
+            Value::Nil => write!(f, "nil"),
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Obj(obj) => write!(f, "{}", obj),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ValueArray {
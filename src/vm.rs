@@ -1,127 +1,899 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    mem,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use crate::{
-    chunk::{self, Chunk, OpCode},
+    chunk::OpCode,
     compiler::compile,
-    debug::{disassemble_chunk, disassemble_instruction},
+    debug::disassemble_function,
+    error::{CompileError, InterpretError, RuntimeError, TraceFrame},
+    host::{self, Handle, Handles},
+    memory::{Heap, HeapStats},
+    object::{
+        Gc, NativeFn, Obj, ObjBoundMethod, ObjClass, ObjClosure, ObjFunction, ObjInstance,
+        ObjNative, ObjString, ObjUpvalue,
+    },
+    trace::{Frame, Tracer},
     value::{print_value, Value},
 };
-const STACK_MAX: usize = 256;
 
-pub struct VM<'a> {
-    pub chunk: Option<&'a Chunk>,
+const UINT8_COUNT: usize = u8::MAX as usize + 1;
+const FRAMES_MAX: usize = 64;
+const STACK_MAX: usize = FRAMES_MAX * UINT8_COUNT;
+
+/// An ongoing function call. `slots` is the index into the VM stack of the
+/// callee itself, so its arguments and locals start right after it.
+struct CallFrame {
+    closure: Gc<ObjClosure>,
     ip: usize,
+    slots: usize,
+}
+
+pub struct VM {
+    frames: Vec<CallFrame>,
+    frames_max: usize,
     stack: Vec<Value>,
-    stack_top: usize,
+    heap: Heap,
+    globals: HashMap<Gc<ObjString>, Value>,
+    /// Upvalues still pointing into the stack, ordered by stack slot.
+    open_upvalues: Vec<Gc<ObjUpvalue>>,
+    /// Interned name of initializer methods, looked up on every construction.
+    init_string: Gc<ObjString>,
+    /// Functions the compiler is still filling in. They are not reachable
+    /// from anything else until compilation finishes.
+    pub(crate) compiler_roots: Vec<Gc<ObjFunction>>,
+    /// Objects the embedder holds a [`Handle`] to.
+    handles: Handles,
+    /// Arguments passed to the script, exposed through `argc` and `argv`
+    /// once [`VM::set_args`] defines them.
+    args: Vec<String>,
+    tracer: Option<Box<dyn Tracer>>,
+    /// Frame count and stack height when the innermost call into the VM
+    /// began. A native can call back in, and an error there only unwinds
+    /// back to this point.
+    entry: (usize, usize),
+}
+
+impl Default for VM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn clock_native(_vm: &mut VM, _args: &[host::Value]) -> Result<host::Value, RuntimeError> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| RuntimeError::new("System clock is before the Unix epoch."))?;
+    Ok(host::Value::Number(elapsed.as_secs_f64()))
 }
 
-#[derive(Debug)]
-pub enum InterpretResultError {
-    INTERPRET_OK,
-    INTERPRET_COMPILE_ERROR,
-    INTERPRET_RUNTIME_ERROR,
+fn argc_native(vm: &mut VM, _args: &[host::Value]) -> Result<host::Value, RuntimeError> {
+    Ok(host::Value::Number(vm.args.len() as f64))
 }
 
-impl<'a> VM<'a> {
+fn argv_native(vm: &mut VM, args: &[host::Value]) -> Result<host::Value, RuntimeError> {
+    let index = match args[0] {
+        host::Value::Number(index) if index.fract() == 0.0 && index >= 0.0 => index as usize,
+        _ => return Err(RuntimeError::new("Argument index must be a whole number.")),
+    };
+    match vm.args.get(index) {
+        Some(arg) => Ok(host::Value::String(arg.clone())),
+        None => Err(RuntimeError::new("Argument index out of range.")),
+    }
+}
+
+impl VM {
     pub fn init_vm(&mut self) {
         self.reset_stack();
     }
+    /// Drops the frames and stack slots of the innermost call into the VM,
+    /// which at the outermost level is all of them.
     fn reset_stack(&mut self) {
-        self.stack_top = 0;
+        let (frames, slots) = self.entry;
+        // Closures that escaped before an error may outlive the stack, so
+        // their upvalues take the values with them.
+        self.close_upvalues(slots);
+        self.stack.truncate(slots);
+        self.frames.truncate(frames);
+    }
+
+    /// Runs `body` as a new call into the VM. See [`VM::reset_stack`].
+    fn enter<T>(
+        &mut self,
+        body: impl FnOnce(&mut Self) -> Result<T, InterpretError>,
+    ) -> Result<T, InterpretError> {
+        let entry = mem::replace(&mut self.entry, (self.frames.len(), self.stack.len()));
+        let result = body(self);
+        self.entry = entry;
+        result
     }
 
-    pub fn push(&mut self, value: Value) {
+    pub(crate) fn push(&mut self, value: Value) {
         self.stack.push(value);
-        self.stack_top += 1;
     }
 
-    pub fn pop(&mut self) -> Value {
-        self.stack_top -= 1;
+    pub(crate) fn pop(&mut self) -> Value {
         self.stack.pop().unwrap()
     }
+
+    fn peek(&self, distance: usize) -> Value {
+        self.stack[self.stack.len() - 1 - distance]
+    }
+
     pub fn new() -> Self {
-        Self {
-            chunk: None,
-            ip: 0,
+        let mut heap = Heap::new();
+        let init_string = heap.copy_string("init");
+        let mut vm = Self {
+            frames: Vec::with_capacity(FRAMES_MAX),
+            frames_max: FRAMES_MAX,
             stack: Vec::with_capacity(STACK_MAX),
-            stack_top: 0,
+            heap,
+            globals: HashMap::new(),
+            open_upvalues: Vec::new(),
+            init_string,
+            compiler_roots: Vec::new(),
+            handles: Rc::new(RefCell::new(HashMap::new())),
+            args: Vec::new(),
+            tracer: None,
+            entry: (0, 0),
+        };
+
+        vm.define_native("clock", 0, clock_native);
+        vm
+    }
+
+    /// Makes `function` callable from scripts as the global `name`.
+    pub fn define_native(&mut self, name: &str, arity: usize, function: NativeFn) {
+        // Both objects stay on the stack until they are stored in the
+        // globals table so a collection in between cannot free them.
+        let name = self.copy_string(name);
+        self.push(Value::Obj(Obj::String(name)));
+        let native = self.alloc(ObjNative::new(arity, function));
+        self.push(Value::Obj(Obj::Native(native)));
+
+        self.globals.insert(name, self.peek(0));
+        self.pop();
+        self.pop();
+    }
+
+    /// Allocates `value` on the heap, collecting garbage first if the heap
+    /// has grown past its threshold. Anything the caller still needs must be
+    /// reachable from a root before calling this.
+    pub(crate) fn alloc<T>(&mut self, value: T) -> Gc<T>
+    where
+        Obj: From<Gc<T>>,
+    {
+        if self.heap.should_collect() {
+            self.collect_garbage();
+        }
+        self.heap.alloc(value)
+    }
+
+    pub(crate) fn copy_string(&mut self, chars: &str) -> Gc<ObjString> {
+        if self.heap.should_collect() {
+            self.collect_garbage();
+        }
+        self.heap.copy_string(chars)
+    }
+
+    pub(crate) fn take_string(&mut self, chars: String) -> Gc<ObjString> {
+        if self.heap.should_collect() {
+            self.collect_garbage();
+        }
+        self.heap.take_string(chars)
+    }
+
+    pub fn heap_stats(&self) -> HeapStats {
+        self.heap.stats()
+    }
+
+    pub fn collect_garbage(&mut self) {
+        #[cfg(feature = "log-gc")]
+        eprintln!("-- gc begin");
+
+        self.mark_roots();
+        self.heap.collect_garbage();
+
+        #[cfg(feature = "log-gc")]
+        eprintln!("-- gc end");
+    }
+
+    fn mark_roots(&mut self) {
+        for &value in &self.stack {
+            self.heap.mark_value(value);
+        }
+        for frame in &self.frames {
+            self.heap.mark_object(Obj::Closure(frame.closure));
+        }
+        for &upvalue in &self.open_upvalues {
+            self.heap.mark_object(Obj::Upvalue(upvalue));
+        }
+        for (&name, &value) in &self.globals {
+            self.heap.mark_object(Obj::String(name));
+            self.heap.mark_value(value);
+        }
+        for &function in &self.compiler_roots {
+            self.heap.mark_object(Obj::Function(function));
+        }
+        for &obj in self.handles.borrow().values() {
+            self.heap.mark_object(obj);
         }
+        self.heap.mark_object(Obj::String(self.init_string));
     }
 
-    pub fn ip(&self) -> u8 {
-        self.chunk.unwrap().code[self.ip]
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().unwrap()
     }
 
-    pub fn interpret(
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let frame = self.frame_mut();
+        let byte = frame.closure.function.chunk.code[frame.ip];
+        frame.ip += 1;
+        byte
+    }
+
+    fn read_short(&mut self) -> u16 {
+        let frame = self.frame_mut();
+        let code = &frame.closure.function.chunk.code;
+        let short = u16::from_be_bytes([code[frame.ip], code[frame.ip + 1]]);
+        frame.ip += 2;
+        short
+    }
+
+    fn read_constant(&mut self) -> Value {
+        let index = self.read_byte();
+        self.frame().closure.function.chunk.constants.values[index as usize]
+    }
+
+    fn read_string(&mut self) -> Gc<ObjString> {
+        match self.read_constant() {
+            Value::Obj(Obj::String(string)) => string,
+            _ => unreachable!("operand is not a string constant"),
+        }
+    }
+
+    fn runtime_error(&mut self, message: &str) -> InterpretError {
+        // Innermost call first, down to the top-level script or the call the
+        // embedder made. Errors raised by the embedding API before any code
+        // runs have no frames at all.
+        let trace = self.frames[self.entry.0..]
+            .iter()
+            .rev()
+            .map(|frame| {
+                let function = frame.closure.function;
+                TraceFrame {
+                    line: function.chunk.lines[frame.ip - 1],
+                    function: function.name.map(|name| name.chars.clone()),
+                }
+            })
+            .collect();
+
+        self.reset_stack();
+        InterpretError::Runtime(RuntimeError {
+            message: message.to_string(),
+            trace,
+        })
+    }
+
+    fn call(&mut self, closure: Gc<ObjClosure>, arg_count: u8) -> Result<(), InterpretError> {
+        if arg_count as usize != closure.function.arity {
+            let message = format!(
+                "Expected {} arguments but got {}.",
+                closure.function.arity, arg_count
+            );
+            return Err(self.runtime_error(&message));
+        }
+
+        if self.frames.len() >= self.frames_max {
+            return Err(self.runtime_error("Stack overflow."));
+        }
+
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            slots: self.stack.len() - arg_count as usize - 1,
+        });
+        Ok(())
+    }
+
+    fn call_value(&mut self, callee: Value, arg_count: u8) -> Result<(), InterpretError> {
+        match callee {
+            Value::Obj(Obj::Closure(closure)) => self.call(closure, arg_count),
+            Value::Obj(Obj::Native(native)) => {
+                if arg_count as usize != native.arity {
+                    let message =
+                        format!("Expected {} arguments but got {}.", native.arity, arg_count);
+                    return Err(self.runtime_error(&message));
+                }
+
+                let args_start = self.stack.len() - arg_count as usize;
+                let args: Vec<_> = self.stack[args_start..]
+                    .iter()
+                    .map(|&arg| self.export_value(arg))
+                    .collect();
+                match (native.function)(self, &args) {
+                    Ok(result) => {
+                        // The arguments are still on the stack while the
+                        // result is converted, which may allocate.
+                        let result = self.import_value(&result);
+                        self.stack.truncate(args_start - 1);
+                        self.push(result);
+                        Ok(())
+                    }
+                    Err(error) => Err(self.runtime_error(&error.message)),
+                }
+            }
+            Value::Obj(Obj::BoundMethod(bound)) => {
+                let slot = self.stack.len() - arg_count as usize - 1;
+                self.stack[slot] = bound.receiver;
+                self.call(bound.method, arg_count)
+            }
+            Value::Obj(Obj::Class(klass)) => {
+                let instance = self.alloc(ObjInstance::new(klass));
+                let slot = self.stack.len() - arg_count as usize - 1;
+                self.stack[slot] = Value::Obj(Obj::Instance(instance));
+
+                match klass.methods.get(&self.init_string) {
+                    Some(&initializer) => self.call(initializer, arg_count),
+                    None if arg_count != 0 => {
+                        let message = format!("Expected 0 arguments but got {}.", arg_count);
+                        Err(self.runtime_error(&message))
+                    }
+                    None => Ok(()),
+                }
+            }
+            _ => Err(self.runtime_error("Can only call functions and classes.")),
+        }
+    }
+
+    fn invoke_from_class(
         &mut self,
-        source: String,
-        chunk: &'a mut Chunk,
-    ) -> Result<InterpretResultError, InterpretResultError> {
-        // let mut chunk = Chunk::init_chunk();
+        klass: Gc<ObjClass>,
+        name: Gc<ObjString>,
+        arg_count: u8,
+    ) -> Result<(), InterpretError> {
+        match klass.methods.get(&name) {
+            Some(&method) => self.call(method, arg_count),
+            None => {
+                let message = format!("Undefined property '{}'.", name.chars);
+                Err(self.runtime_error(&message))
+            }
+        }
+    }
+
+    /// Calls a method straight off the receiver without materialising a
+    /// bound method. Fields shadow methods, so a callable stored in a field
+    /// is called as a plain value.
+    fn invoke(&mut self, name: Gc<ObjString>, arg_count: u8) -> Result<(), InterpretError> {
+        let instance = match self.peek(arg_count as usize) {
+            Value::Obj(Obj::Instance(instance)) => instance,
+            _ => return Err(self.runtime_error("Only instances have methods.")),
+        };
 
-        if compile(source, chunk) {
-            return Err(InterpretResultError::INTERPRET_COMPILE_ERROR);
+        if let Some(&value) = instance.fields.get(&name) {
+            let slot = self.stack.len() - arg_count as usize - 1;
+            self.stack[slot] = value;
+            return self.call_value(value, arg_count);
         }
 
-        self.chunk = Some(chunk);
-        self.ip = self.chunk.unwrap().code[0] as usize;
+        self.invoke_from_class(instance.klass, name, arg_count)
+    }
 
-        let result = self.run();
+    /// Replaces the instance on top of the stack with its method `name`
+    /// bound to it.
+    fn bind_method(
+        &mut self,
+        klass: Gc<ObjClass>,
+        name: Gc<ObjString>,
+    ) -> Result<(), InterpretError> {
+        let method = match klass.methods.get(&name) {
+            Some(&method) => method,
+            None => {
+                let message = format!("Undefined property '{}'.", name.chars);
+                return Err(self.runtime_error(&message));
+            }
+        };
 
-        return result;
+        let bound = self.alloc(ObjBoundMethod::new(self.peek(0), method));
+        self.pop();
+        self.push(Value::Obj(Obj::BoundMethod(bound)));
+        Ok(())
     }
 
-    pub fn run(&mut self) -> Result<InterpretResultError, InterpretResultError> {
+    fn define_method(&mut self, name: Gc<ObjString>) {
+        let method = match self.peek(0) {
+            Value::Obj(Obj::Closure(closure)) => closure,
+            _ => unreachable!("method body is not a closure"),
+        };
+        if let Value::Obj(Obj::Class(mut klass)) = self.peek(1) {
+            klass.methods.insert(name, method);
+        }
+        self.pop();
+    }
+
+    fn capture_upvalue(&mut self, location: usize) -> Gc<ObjUpvalue> {
+        let index = match self
+            .open_upvalues
+            .binary_search_by_key(&location, |upvalue| upvalue.location)
+        {
+            Ok(existing) => return self.open_upvalues[existing],
+            Err(index) => index,
+        };
+
+        let created = self.alloc(ObjUpvalue::new(location));
+        self.open_upvalues.insert(index, created);
+        created
+    }
+
+    /// Closes every open upvalue that points at `last` or above, copying the
+    /// variable off the stack before its slot is discarded.
+    fn close_upvalues(&mut self, last: usize) {
+        while let Some(&upvalue) = self.open_upvalues.last() {
+            if upvalue.location < last {
+                break;
+            }
+            let mut upvalue = upvalue;
+            upvalue.closed = Some(self.stack[upvalue.location]);
+            self.open_upvalues.pop();
+        }
+    }
+
+    fn concatenate(&mut self) {
+        let b = self.pop();
+        let a = self.pop();
+        if let (Value::Obj(Obj::String(a)), Value::Obj(Obj::String(b))) = (a, b) {
+            let result = self.take_string(format!("{}{}", a.chars, b.chars));
+            self.push(Value::Obj(Obj::String(result)));
+        }
+    }
+
+    /// Compiles and runs `source`. Globals defined by it stay in the VM, so
+    /// later calls see them.
+    pub fn interpret(&mut self, source: &str) -> Result<(), InterpretError> {
+        let function = compile(source.to_string(), false, self)?;
+        self.run_script(function)
+    }
+
+    /// Like [`VM::interpret`], but for interactive use: a trailing
+    /// expression without a semicolon has its value printed.
+    pub fn interpret_repl(&mut self, source: &str) -> Result<(), InterpretError> {
+        let function = compile(source.to_string(), true, self)?;
+        self.run_script(function)
+    }
+
+    fn run_script(&mut self, function: Gc<ObjFunction>) -> Result<(), InterpretError> {
+        self.enter(|vm| {
+            vm.push(Value::Obj(Obj::Function(function)));
+            let closure = vm.alloc(ObjClosure::new(function));
+            vm.pop();
+            vm.push(Value::Obj(Obj::Closure(closure)));
+            vm.call(closure, 0)?;
+
+            vm.run()?;
+            Ok(())
+        })
+    }
+
+    /// Calls the global function `name` with `args` and returns its result.
+    /// Works for closures, natives and classes alike. Panics if an argument
+    /// holds a handle from another VM.
+    pub fn call_function(
+        &mut self,
+        name: &str,
+        args: &[host::Value],
+    ) -> Result<host::Value, InterpretError> {
+        self.enter(|vm| vm.call_global(name, args))
+    }
+
+    fn call_global(
+        &mut self,
+        name: &str,
+        args: &[host::Value],
+    ) -> Result<host::Value, InterpretError> {
+        let callee = match self.global(name) {
+            Some(callee) => callee,
+            None => {
+                let message = format!("Undefined variable '{}'.", name);
+                return Err(self.runtime_error(&message));
+            }
+        };
+        let arg_count = match u8::try_from(args.len()) {
+            Ok(arg_count) => arg_count,
+            Err(_) => return Err(self.runtime_error("Can't have more than 255 arguments.")),
+        };
+
+        self.push(callee);
+        for arg in args {
+            let arg = self.import_value(arg);
+            self.push(arg);
+        }
+
+        let depth = self.frames.len();
+        self.call_value(callee, arg_count)?;
+        if self.frames.len() == depth {
+            // Natives and classes without an initializer finish immediately.
+            let result = self.pop();
+            return Ok(self.export_value(result));
+        }
+        let result = self.run()?;
+        Ok(self.export_value(result))
+    }
+
+    /// Every global variable with its value, sorted by name.
+    pub fn globals(&self) -> Vec<(String, host::Value)> {
+        let mut globals: Vec<_> = self
+            .globals
+            .iter()
+            .map(|(name, &value)| (name.chars.clone(), self.export_value(value)))
+            .collect();
+        globals.sort_by(|a, b| a.0.cmp(&b.0));
+        globals
+    }
+
+    /// The value stack, bottom first.
+    pub fn stack(&self) -> Vec<host::Value> {
+        self.stack
+            .iter()
+            .map(|&value| self.export_value(value))
+            .collect()
+    }
+
+    /// Compiles `source` and prints its bytecode without running it.
+    pub fn disassemble(&mut self, source: &str) -> Result<(), CompileError> {
+        let function = compile(source.to_string(), false, self)?;
+        disassemble_function(&function);
+        Ok(())
+    }
+
+    /// Like [`VM::disassemble`], but compiles `source` as a REPL line.
+    pub fn disassemble_repl(&mut self, source: &str) -> Result<(), CompileError> {
+        let function = compile(source.to_string(), true, self)?;
+        disassemble_function(&function);
+        Ok(())
+    }
+
+    /// Sets the arguments scripts see through the `argc()` and `argv(i)`
+    /// natives, and defines those natives.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+        self.define_native("argc", 0, argc_native);
+        self.define_native("argv", 1, argv_native);
+    }
+
+    /// Sets how deep calls may nest before the VM reports a stack overflow.
+    /// The top-level script counts as one frame. Defaults to 64.
+    pub fn set_max_frames(&mut self, max: usize) {
+        self.frames_max = max;
+    }
+
+    /// Installs a hook called before every instruction, or removes it.
+    pub fn set_tracer(&mut self, tracer: Option<Box<dyn Tracer>>) {
+        self.tracer = tracer;
+    }
+
+    /// Reads the global `name`.
+    pub fn get_global(&self, name: &str) -> Option<host::Value> {
+        let value = self.global(name)?;
+        Some(self.export_value(value))
+    }
+
+    fn global(&self, name: &str) -> Option<Value> {
+        let name = self.heap.find_string(name)?;
+        self.globals.get(&name).copied()
+    }
+
+    /// Panics if `value` holds a handle from another VM.
+    pub fn set_global(&mut self, name: &str, value: host::Value) {
+        let value = self.import_value(&value);
+        // Keep the value reachable while interning the name.
+        self.push(value);
+        let name = self.copy_string(name);
+        self.globals.insert(name, value);
+        self.pop();
+    }
+
+    /// Copies `value` out for the embedder. Objects other than strings get
+    /// a handle that keeps them alive.
+    fn export_value(&self, value: Value) -> host::Value {
+        match value {
+            Value::Nil => host::Value::Nil,
+            Value::Bool(boolean) => host::Value::Bool(boolean),
+            Value::Number(number) => host::Value::Number(number),
+            Value::Obj(Obj::String(string)) => host::Value::String(string.chars.clone()),
+            Value::Obj(obj) => host::Value::Object(Handle::new(&self.handles, obj)),
+        }
+    }
+
+    /// Brings a value from the embedder into the heap. Copying a string in
+    /// may collect garbage, so values converted earlier must be rooted.
+    fn import_value(&mut self, value: &host::Value) -> Value {
+        match value {
+            host::Value::Nil => Value::Nil,
+            host::Value::Bool(boolean) => Value::Bool(*boolean),
+            host::Value::Number(number) => Value::Number(*number),
+            host::Value::String(chars) => Value::Obj(Obj::String(self.copy_string(chars))),
+            host::Value::Object(handle) => Value::Obj(
+                handle
+                    .get(&self.handles)
+                    .expect("handle used with a VM that did not create it"),
+            ),
+        }
+    }
+
+    /// Runs until the frame that is current on entry returns, yielding the
+    /// value it returned.
+    fn run(&mut self) -> Result<Value, InterpretError> {
+        let base = self.frames.len() - 1;
+
         macro_rules! binary_op {
-            ($op:tt) => {
-                let b:f64 = self.pop();
-                let a:f64 = self.pop();
-                self.push(a $op b);
+            ($value_type:path, $op:tt) => {
+                if let (Value::Number(b), Value::Number(a)) = (self.peek(0), self.peek(1)) {
+                    self.pop();
+                    self.pop();
+                    self.push($value_type(a $op b));
+                } else {
+                    return Err(self.runtime_error("Operands must be numbers."));
+                }
             };
         }
-        #[allow(clippy::never_loop)]
         loop {
-            #[cfg(feature = "debug")]
-            {
-                for slot in &self.stack {
-                    print!("[ ");
-                    print_value(slot);
-                    print!(" ]")
-                }
-                disassemble_instruction(self.chunk.unwrap(), &(self.ip as usize));
+            if let Some(mut tracer) = self.tracer.take() {
+                let frame = self.frames.last().unwrap();
+                let frame = Frame {
+                    function: frame.closure.function,
+                    ip: frame.ip,
+                };
+                tracer.trace(&frame, &self.stack());
+                self.tracer = Some(tracer);
             }
 
-            let instruction = self.chunk.unwrap().code[self.ip];
-            self.ip += 1;
+            let instruction = self.read_byte();
             if let Ok(instruction) = instruction.try_into() {
                 match instruction {
-                    OpCode::OP_RETURN => {
+                    OpCode::OP_PRINT => {
                         print_value(&self.pop());
                         println!();
-                        return Ok(InterpretResultError::INTERPRET_OK);
+                    }
+                    OpCode::OP_JUMP => {
+                        let offset = self.read_short();
+                        self.frame_mut().ip += offset as usize;
+                    }
+                    OpCode::OP_JUMP_IF_FALSE => {
+                        let offset = self.read_short();
+                        if self.peek(0).is_falsey() {
+                            self.frame_mut().ip += offset as usize;
+                        }
+                    }
+                    OpCode::OP_LOOP => {
+                        let offset = self.read_short();
+                        self.frame_mut().ip -= offset as usize;
+                    }
+                    OpCode::OP_GET_UPVALUE => {
+                        let slot = self.read_byte() as usize;
+                        let upvalue = self.frame().closure.upvalues[slot];
+                        let value = match upvalue.closed {
+                            Some(value) => value,
+                            None => self.stack[upvalue.location],
+                        };
+                        self.push(value);
+                    }
+                    OpCode::OP_SET_UPVALUE => {
+                        let slot = self.read_byte() as usize;
+                        let mut upvalue = self.frame().closure.upvalues[slot];
+                        let value = self.peek(0);
+                        match upvalue.closed {
+                            Some(_) => upvalue.closed = Some(value),
+                            None => self.stack[upvalue.location] = value,
+                        }
+                    }
+                    OpCode::OP_GET_PROPERTY => {
+                        let instance = match self.peek(0) {
+                            Value::Obj(Obj::Instance(instance)) => instance,
+                            _ => return Err(self.runtime_error("Only instances have properties.")),
+                        };
+                        let name = self.read_string();
+
+                        match instance.fields.get(&name) {
+                            Some(&value) => {
+                                self.pop();
+                                self.push(value);
+                            }
+                            None => self.bind_method(instance.klass, name)?,
+                        }
+                    }
+                    OpCode::OP_SET_PROPERTY => {
+                        let mut instance = match self.peek(1) {
+                            Value::Obj(Obj::Instance(instance)) => instance,
+                            _ => return Err(self.runtime_error("Only instances have fields.")),
+                        };
+                        let name = self.read_string();
+
+                        instance.fields.insert(name, self.peek(0));
+                        let value = self.pop();
+                        self.pop();
+                        self.push(value);
+                    }
+                    OpCode::OP_CALL => {
+                        let arg_count = self.read_byte();
+                        self.call_value(self.peek(arg_count as usize), arg_count)?;
+                    }
+                    OpCode::OP_CLOSURE => {
+                        let function = match self.read_constant() {
+                            Value::Obj(Obj::Function(function)) => function,
+                            _ => unreachable!("operand is not a function constant"),
+                        };
+                        let mut closure = self.alloc(ObjClosure::new(function));
+                        self.push(Value::Obj(Obj::Closure(closure)));
+
+                        for _ in 0..function.upvalue_count {
+                            let is_local = self.read_byte() == 1;
+                            let index = self.read_byte() as usize;
+                            let upvalue = if is_local {
+                                self.capture_upvalue(self.frame().slots + index)
+                            } else {
+                                self.frame().closure.upvalues[index]
+                            };
+                            closure.upvalues.push(upvalue);
+                        }
+                    }
+                    OpCode::OP_CLOSE_UPVALUE => {
+                        self.close_upvalues(self.stack.len() - 1);
+                        self.pop();
+                    }
+                    OpCode::OP_RETURN => {
+                        let result = self.pop();
+                        let frame = self.frames.pop().unwrap();
+                        self.close_upvalues(frame.slots);
+                        self.stack.truncate(frame.slots);
+                        if self.frames.len() == base {
+                            return Ok(result);
+                        }
+
+                        self.push(result);
                     }
                     OpCode::OP_NEGATE => {
-                        let val = self.pop();
-                        self.push(-val);
+                        if let Value::Number(number) = self.peek(0) {
+                            self.pop();
+                            self.push(Value::Number(-number));
+                        } else {
+                            return Err(self.runtime_error("Operand must be a number."));
+                        }
+                    }
+                    OpCode::OP_NOT => {
+                        let value = self.pop();
+                        self.push(Value::Bool(value.is_falsey()));
+                    }
+                    OpCode::OP_EQUAL => {
+                        let b = self.pop();
+                        let a = self.pop();
+                        self.push(Value::Bool(a == b));
                     }
-                    OpCode::OP_ADD => {
-                        binary_op! {+};
+                    OpCode::OP_GREATER => {
+                        binary_op! {Value::Bool, >};
                     }
+                    OpCode::OP_LESS => {
+                        binary_op! {Value::Bool, <};
+                    }
+                    OpCode::OP_ADD => match (self.peek(0), self.peek(1)) {
+                        (Value::Obj(Obj::String(_)), Value::Obj(Obj::String(_))) => {
+                            self.concatenate();
+                        }
+                        (Value::Number(b), Value::Number(a)) => {
+                            self.pop();
+                            self.pop();
+                            self.push(Value::Number(a + b));
+                        }
+                        _ => {
+                            return Err(
+                                self.runtime_error("Operands must be two numbers or two strings.")
+                            );
+                        }
+                    },
                     OpCode::OP_SUBTRACT => {
-                        binary_op! {-};
+                        binary_op! {Value::Number, -};
                     }
                     OpCode::OP_DIVIDE => {
-                        binary_op! {/};
+                        binary_op! {Value::Number, /};
                     }
                     OpCode::OP_MULTIPLY => {
-                        binary_op! {*};
+                        binary_op! {Value::Number, *};
+                    }
+                    OpCode::OP_NIL => self.push(Value::Nil),
+                    OpCode::OP_TRUE => self.push(Value::Bool(true)),
+                    OpCode::OP_FALSE => self.push(Value::Bool(false)),
+                    OpCode::OP_POP => {
+                        self.pop();
+                    }
+                    OpCode::OP_GET_LOCAL => {
+                        let slot = self.frame().slots + self.read_byte() as usize;
+                        self.push(self.stack[slot]);
+                    }
+                    OpCode::OP_SET_LOCAL => {
+                        let slot = self.frame().slots + self.read_byte() as usize;
+                        self.stack[slot] = self.peek(0);
+                    }
+                    OpCode::OP_GET_GLOBAL => {
+                        let name = self.read_string();
+                        match self.globals.get(&name) {
+                            Some(&value) => self.push(value),
+                            None => {
+                                let message = format!("Undefined variable '{}'.", name.chars);
+                                return Err(self.runtime_error(&message));
+                            }
+                        }
+                    }
+                    OpCode::OP_DEFINE_GLOBAL => {
+                        let name = self.read_string();
+                        self.globals.insert(name, self.peek(0));
+                        self.pop();
+                    }
+                    OpCode::OP_SET_GLOBAL => {
+                        let name = self.read_string();
+                        if !self.globals.contains_key(&name) {
+                            let message = format!("Undefined variable '{}'.", name.chars);
+                            return Err(self.runtime_error(&message));
+                        }
+                        self.globals.insert(name, self.peek(0));
+                    }
+                    OpCode::OP_INVOKE => {
+                        let method = self.read_string();
+                        let arg_count = self.read_byte();
+                        self.invoke(method, arg_count)?;
+                    }
+                    OpCode::OP_SUPER_INVOKE => {
+                        let method = self.read_string();
+                        let arg_count = self.read_byte();
+                        let superclass = match self.pop() {
+                            Value::Obj(Obj::Class(superclass)) => superclass,
+                            _ => unreachable!("superclass is not a class"),
+                        };
+                        self.invoke_from_class(superclass, method, arg_count)?;
+                    }
+                    OpCode::OP_GET_SUPER => {
+                        let name = self.read_string();
+                        let superclass = match self.pop() {
+                            Value::Obj(Obj::Class(superclass)) => superclass,
+                            _ => unreachable!("superclass is not a class"),
+                        };
+                        self.bind_method(superclass, name)?;
+                    }
+                    OpCode::OP_INHERIT => {
+                        let superclass = match self.peek(1) {
+                            Value::Obj(Obj::Class(superclass)) => superclass,
+                            _ => return Err(self.runtime_error("Superclass must be a class.")),
+                        };
+                        if let Value::Obj(Obj::Class(mut subclass)) = self.peek(0) {
+                            // Copy-down inheritance: methods defined later in
+                            // the subclass body overwrite these.
+                            subclass.methods.extend(&superclass.methods);
+                        }
+                        self.pop();
+                    }
+                    OpCode::OP_METHOD => {
+                        let name = self.read_string();
+                        self.define_method(name);
+                    }
+                    OpCode::OP_CLASS => {
+                        let name = self.read_string();
+                        let klass = self.alloc(ObjClass::new(name));
+                        self.push(Value::Obj(Obj::Class(klass)));
                     }
                     OpCode::OP_CONSTANT => {
-                        let constant = self.chunk.unwrap().constants.values[self.ip() as usize];
+                        let constant = self.read_constant();
                         self.push(constant);
-                        self.ip += 1;
                     }
                 }
             } else {
-                return Err(InterpretResultError::INTERPRET_COMPILE_ERROR);
+                let message = format!("Unknown opcode {}.", instruction);
+                return Err(self.runtime_error(&message));
             };
         }
     }
@@ -0,0 +1,21 @@
+use roxty::{InterpretError, VM};
+
+fn first_diagnostic(source: &str) -> (usize, usize) {
+    match VM::new().interpret(source) {
+        Err(InterpretError::Compile(error)) => {
+            let diagnostic = &error.diagnostics[0];
+            (diagnostic.line, diagnostic.column)
+        }
+        other => panic!("expected a compile error, got {:?}", other),
+    }
+}
+
+#[test]
+fn columns_count_characters_not_bytes() {
+    assert_eq!(first_diagnostic("var a = 1 +;"), (1, 12));
+    assert_eq!(first_diagnostic("var s = \"é\"; var b = 1 +;"), (1, 25));
+    assert_eq!(
+        first_diagnostic("print \"日本\";\nprint \"語\" +;"),
+        (2, 12)
+    );
+}
@@ -0,0 +1,33 @@
+use roxty::{InterpretError, RuntimeError, Value, VM};
+
+// Calls back into the VM and turns a failure into a value, so the script
+// that called the native keeps running.
+fn try_call(vm: &mut VM, _args: &[Value]) -> Result<Value, RuntimeError> {
+    match vm.call_function("boom", &[]) {
+        Ok(value) => Ok(value),
+        Err(InterpretError::Runtime(error)) => Ok(Value::String(error.message)),
+        Err(error) => Err(RuntimeError::new(error.to_string())),
+    }
+}
+
+#[test]
+fn errors_in_calls_from_natives_leave_the_caller_running() {
+    let mut vm = VM::new();
+    vm.define_native("try_call", 0, try_call);
+    vm.interpret(
+        r#"
+fun boom() { return 1 + nil; }
+fun outer() { var local = "kept"; var result = try_call(); return local + ": " + result; }
+var first = outer();
+var second = outer();
+"#,
+    )
+    .unwrap();
+
+    let second = vm.get_global("second").unwrap();
+    assert_eq!(
+        second.to_string(),
+        "kept: Operands must be two numbers or two strings."
+    );
+    assert!(vm.stack().is_empty());
+}
@@ -0,0 +1,85 @@
+use roxty::{Value, VM};
+
+// Freeing objects in the wrong order only shows up as reads of freed memory,
+// so these are most useful under AddressSanitizer and with `stress-gc`.
+
+// Every call leaves behind closures and functions that become garbage
+// together, and the top-level script function dies after each `interpret`.
+const CLOSURES: &str = r#"
+fun makeAdder(n) {
+  fun add(x) { return x + n; }
+  return add;
+}
+var total = 0;
+for (var i = 0; i < 50; i = i + 1) {
+  total = total + makeAdder(i)(1);
+}
+fun mk() { var s = "cap" + "tured"; fun get() { return s; } return get; }
+var last = mk()();
+"#;
+
+#[test]
+fn closures_survive_collections_across_interpret_calls() {
+    let mut vm = VM::new();
+    for _ in 0..5 {
+        vm.interpret(CLOSURES).unwrap();
+        vm.collect_garbage();
+        vm.interpret("print mk()();").unwrap();
+    }
+    vm.collect_garbage();
+
+    assert_eq!(vm.get_global("total"), Some(Value::Number(1275.0)));
+    let last = vm.get_global("last").unwrap();
+    assert_eq!(last.to_string(), "captured");
+}
+
+// Builds a fresh string on every call, and collects while the native
+// still holds its argument.
+fn shout(vm: &mut VM, args: &[Value]) -> Result<Value, roxty::RuntimeError> {
+    vm.collect_garbage();
+    Ok(Value::String(format!("{}!", args[0])))
+}
+
+#[test]
+fn handles_keep_objects_alive_while_any_copy_exists() {
+    let mut vm = VM::new();
+    vm.define_native("shout", 1, shout);
+    vm.interpret(CLOSURES).unwrap();
+
+    let get = vm.call_function("mk", &[]).unwrap();
+    let copy = get.clone();
+    drop(get);
+    vm.interpret("mk = nil;").unwrap();
+    vm.collect_garbage();
+
+    vm.set_global("get", copy);
+    vm.interpret(r#"var junk = "x"; for (var i = 0; i < 100; i = i + 1) junk = shout(junk);"#)
+        .unwrap();
+    vm.collect_garbage();
+    assert_eq!(
+        vm.call_function("get", &[]),
+        Ok(Value::String("captured".to_string()))
+    );
+    assert_eq!(
+        vm.call_function("shout", &[Value::Number(1.0)]),
+        Ok(Value::String("1!".to_string()))
+    );
+}
+
+#[test]
+fn handles_outliving_their_vm_are_harmless() {
+    let mut vm = VM::new();
+    vm.interpret("class Box {} var box = Box();").unwrap();
+    let instance = vm.get_global("box").unwrap();
+    drop(vm);
+    assert_eq!(instance.to_string(), "Box instance");
+}
+
+#[test]
+#[should_panic(expected = "handle used with a VM that did not create it")]
+fn handles_are_only_accepted_by_their_vm() {
+    let mut first = VM::new();
+    first.interpret("fun f() {}").unwrap();
+    let f = first.get_global("f").unwrap();
+    VM::new().set_global("f", f);
+}
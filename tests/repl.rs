@@ -0,0 +1,51 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Feeds `input` to an interactive session and returns its stdout and stderr.
+fn repl(input: &str) -> (String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_roxty"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+// A runtime error unwinds `f` while `h` still captures its local `x`, which
+// has to be closed over rather than left pointing into the dead stack.
+#[test]
+fn session_recovers_from_runtime_error_with_captured_locals() {
+    let (stdout, stderr) = repl(
+        "var g;
+fun f() {
+  var x = 1;
+  fun h() { return x; }
+  g = h;
+  nil();
+}
+f();
+print g();
+var y = \"still\" + \" here\";
+print y;
+",
+    );
+
+    assert!(stderr.contains("Can only call functions and classes."));
+    let stdout = stdout.replace("... ", "").replace("> ", "");
+    let printed: Vec<_> = stdout.lines().filter(|line| !line.is_empty()).collect();
+    assert_eq!(printed, ["1", "still here"]);
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "tree-walk")]
+
+use std::process::Command;
+
+/// Runs the tree-walk engine with `args` and returns what it printed.
+fn run_tree(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_roxty"))
+        .arg("--engine=tree")
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn counter_closures_share_their_captured_variable() {
+    assert_eq!(run_tree(&["fun.lox"]), "1\n2\n");
+}
+
+#[test]
+fn closures_keep_the_binding_they_were_resolved_to() {
+    let source = r#"
+var a = "global";
+{
+  fun showA() { print a; }
+  showA();
+  var a = "block";
+  showA();
+}
+"#;
+    assert_eq!(run_tree(&["-e", source]), "global\nglobal\n");
+}